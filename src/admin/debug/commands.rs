@@ -21,7 +21,7 @@
 	ServerName,
 };
 use service::rooms::{
-	short::{ShortEventId, ShortRoomId},
+	short::{ShortEventId, ShortRoomId, ShortStateKey},
 	state_compressor::HashSetCompressStateEvent,
 };
 use tracing_subscriber::EnvFilter;
@@ -147,7 +147,7 @@ pub(super) async fn get_short_pdu(
 ) -> Result<RoomMessageEventContent> {
 	let pdu_id: RawPduId = PduId {
 		shortroomid,
-		shorteventid: shorteventid.into(),
+		shorteventid: shorteventid.get().into(),
 	}
 	.into();
 
@@ -634,7 +634,7 @@ pub(super) async fn force_set_room_state_from_server(
 
 	let room_version = self.services.rooms.state.get_room_version(&room_id).await?;
 
-	let mut state: HashMap<u64, OwnedEventId> = HashMap::new();
+	let mut state: HashMap<ShortStateKey, OwnedEventId> = HashMap::new();
 
 	let remote_state_response = self
 		.services
@@ -689,7 +689,7 @@ pub(super) async fn force_set_room_state_from_server(
 				.rooms
 				.short
 				.get_or_create_shortstatekey(&pdu.kind.to_string().into(), state_key)
-				.await;
+				.await?;
 
 			state.insert(shortstatekey, pdu.event_id.clone());
 		}
@@ -1,10 +1,11 @@
-use std::fmt::Write;
+use std::{fmt::Write, time::Instant};
 
-use conduwuit::Result;
+use conduwuit::{utils::time, Err, Result};
 use futures::StreamExt;
 use ruma::{
 	events::room::message::RoomMessageEventContent, OwnedRoomId, RoomId, ServerName, UserId,
 };
+use service::resolver::actual::WellKnown;
 
 use crate::{admin_command, get_room_info};
 
@@ -39,6 +40,135 @@ pub(super) async fn incoming_federation(&self) -> Result<RoomMessageEventContent
 	Ok(RoomMessageEventContent::text_plain(&msg))
 }
 
+#[admin_command]
+pub(super) async fn resolve_trace(
+	&self,
+	server_name: Box<ServerName>,
+) -> Result<RoomMessageEventContent> {
+	let trace = self
+		.services
+		.resolver
+		.trace_matrix_destination(&server_name)
+		.await?;
+
+	Ok(RoomMessageEventContent::notice_markdown(format!("```\n{trace}```")))
+}
+
+#[admin_command]
+pub(super) async fn test_wellknown(
+	&self,
+	server_name: Box<ServerName>,
+) -> Result<RoomMessageEventContent> {
+	let url = format!("https://{server_name}/.well-known/matrix/server");
+	let resolver = &self.services.resolver;
+	let delegated = match resolver.query_well_known(server_name.as_str()).await {
+		| Err(e) => {
+			writeln!(self, "1. GET {url}: failed: {e}").await?;
+			None
+		},
+		| Ok(WellKnown { status, m_server }) => {
+			let status = status.map_or_else(|| "no response".to_owned(), |s| s.to_string());
+			match m_server {
+				| Err(reason) => {
+					writeln!(self, "1. GET {url}: {status}; no usable m.server: {reason}")
+						.await?;
+					None
+				},
+				| Ok(m_server) => {
+					writeln!(self, "1. GET {url}: {status}; m.server: {m_server}").await?;
+					Some(m_server)
+				},
+			}
+		},
+	};
+
+	match delegated {
+		| Some(delegated) => writeln!(self, "2. Delegated to {delegated}").await?,
+		| None => writeln!(self, "2. Not delegated; {server_name} is resolved itself").await?,
+	}
+
+	match resolver.resolve_matrix_destination(&server_name).await {
+		| Err(e) => writeln!(self, "3. Resolution failed: {e}").await?,
+		| Ok(addrs) => {
+			if let Ok(cached) = resolver.cache.get_destination(&server_name).await {
+				writeln!(self, "3. Destination: {}", cached.dest).await?;
+			}
+
+			writeln!(self, "4. Addresses: {addrs:?}").await?;
+		},
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+pub(super) async fn test_user(&self, user_id: Box<UserId>) -> Result<RoomMessageEventContent> {
+	use ruma::api::federation::discovery::get_server_version;
+
+	let server_name = user_id.server_name();
+	if self.services.globals.server_is_ours(server_name) {
+		return Err!("{user_id} is a local user; there is no federation to test.");
+	}
+
+	let trace = self
+		.services
+		.resolver
+		.trace_matrix_destination(server_name)
+		.await?;
+
+	writeln!(self, "1. Resolution trace of {server_name}:\n```\n{trace}```").await?;
+
+	let resolver = &self.services.resolver;
+	let failed = match resolver.resolve_matrix_destination(server_name).await {
+		| Err(e) => {
+			writeln!(self, "2. Destination: failed: {e}").await?;
+			Some("resolving the destination")
+		},
+		| Ok(addrs) => {
+			if let Ok(cached) = resolver.cache.get_destination(server_name).await {
+				writeln!(self, "2. Destination: {} at {addrs:?}", cached.dest).await?;
+			} else {
+				writeln!(self, "2. Destination: {addrs:?}").await?;
+			}
+
+			let timer = Instant::now();
+			let request = get_server_version::v1::Request {};
+			match self
+				.services
+				.sending
+				.send_federation_request(server_name, request)
+				.await
+			{
+				| Err(e) => {
+					writeln!(self, "3. GET /_matrix/federation/v1/version: failed: {e}").await?;
+					Some("requesting /version")
+				},
+				| Ok(response) => {
+					let elapsed = timer.elapsed();
+					let server = response.server.as_ref();
+					let name = server.and_then(|server| server.name.as_deref());
+					let version = server.and_then(|server| server.version.as_deref());
+					writeln!(
+						self,
+						"3. GET /_matrix/federation/v1/version: {} {} in {elapsed:?}",
+						name.unwrap_or("unknown"),
+						version.unwrap_or("(no version)"),
+					)
+					.await?;
+					None
+				},
+			}
+		},
+	};
+
+	match failed {
+		| None => writeln!(self, "\n**PASS**: federation with {server_name} works.").await?,
+		| Some(stage) => writeln!(self, "\n**FAIL** at {stage}.").await?,
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
 #[admin_command]
 pub(super) async fn fetch_support_well_known(
 	&self,
@@ -87,6 +217,34 @@ pub(super) async fn fetch_support_well_known(
 	)))
 }
 
+#[admin_command]
+pub(super) async fn address_hints(&self) -> Result<RoomMessageEventContent> {
+	let hints = self.services.resolver.hints.hints();
+	if hints.is_empty() {
+		return Ok(RoomMessageEventContent::notice_plain("No address hints recorded."));
+	}
+
+	writeln!(self, "| Host | Address | Recorded |").await?;
+	writeln!(self, "| ---- | ------- | -------- |").await?;
+	for (host, ip, recorded) in hints {
+		let age = time::pretty(recorded.elapsed().unwrap_or_default());
+		writeln!(self, "| {host} | {ip} | {age} ago |").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+pub(super) async fn clear_hint(&self, host: String) -> Result<RoomMessageEventContent> {
+	if !self.services.resolver.hints.remove(&host) {
+		return Err!("No address hint is recorded for {host}.");
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(format!(
+		"Cleared the address hint of {host}."
+	)))
+}
+
 #[admin_command]
 pub(super) async fn remote_user_in_rooms(
 	&self,
@@ -35,6 +35,49 @@ pub(super) enum FederationCommand {
 		server_name: Box<ServerName>,
 	},
 
+	/// - Trace each step of resolving a server name to the addresses to connect
+	///   to
+	///
+	/// Shows the cache layers consulted, the IP literal check, the
+	/// .well-known delegation, SRV and A/AAAA records, and the addresses
+	/// finally chosen.
+	ResolveTrace {
+		server_name: Box<ServerName>,
+	},
+
+	/// - Test .well-known delegation of a remote server end-to-end
+	///
+	/// Fetches its /.well-known/matrix/server as the resolver does, within
+	/// `well_known_max_size`, and reports the HTTP status with the delegated
+	/// target or why there is none, then resolves the server name through the
+	/// destination cache and resolver, printing the final addresses.
+	TestWellknown {
+		server_name: Box<ServerName>,
+	},
+
+	/// - Test federation with the server of a user end-to-end
+	///
+	/// Traces the resolution of the user's server name, resolves its
+	/// destination as federation requests do, then requests its
+	/// /_matrix/federation/v1/version, summarising which stage failed if any.
+	TestUser {
+		user_id: Box<UserId>,
+	},
+
+	/// - List the address each host was last successfully connected to
+	///
+	/// With `dns_prefer_last_good_address` enabled these addresses are tried
+	/// first when connecting to the host again. Shows when each was recorded.
+	AddressHints,
+
+	/// - Forget the last good address of a host
+	///
+	/// The host is the one connected to, i.e. the delegated destination
+	/// rather than necessarily the server name.
+	ClearHint {
+		host: String,
+	},
+
 	/// - Lists all the rooms we share/track with the specified *remote* user
 	RemoteUserInRooms {
 		user_id: Box<UserId>,
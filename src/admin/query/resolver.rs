@@ -1,5 +1,14 @@
+use std::{
+	net::IpAddr,
+	time::{Duration, Instant, SystemTime},
+};
+
 use clap::Subcommand;
-use conduwuit::{utils::time, Result};
+use conduwuit::{
+	err,
+	utils::{math::Expected, stream::IterStream, time},
+	Err, Result,
+};
 use futures::StreamExt;
 use ruma::{events::room::message::RoomMessageEventContent, OwnedServerName};
 
@@ -18,6 +27,92 @@ pub(crate) enum ResolverCommand {
 	OverridesCache {
 		name: Option<String>,
 	},
+
+	/// Benchmark resolution latency of a host through the live resolver
+	Bench {
+		host: String,
+
+		/// Number of resolutions to perform, at most 1000
+		#[arg(default_value = "10")]
+		count: usize,
+
+		/// Perform the resolutions concurrently, up to 32 at a time, rather
+		/// than sequentially
+		#[arg(short, long)]
+		concurrent: bool,
+	},
+
+	/// List names whose resolution is refused by the circuit breaker
+	OpenCircuits,
+
+	/// Count queries issued to the resolver by record type since startup
+	QueryCounts,
+
+	/// Pin a name to the given addresses for a limited time, bypassing DNS;
+	/// normal resolution resumes once the TTL passes. Pins are stored in the
+	/// database along with their expiry and so survive restarts
+	PinOverride {
+		name: String,
+
+		#[arg(required = true)]
+		ips: Vec<IpAddr>,
+
+		/// Port to connect to when the override is used
+		#[arg(short, long, default_value = "8448")]
+		port: u16,
+
+		/// Seconds until the override is removed
+		#[arg(short, long, default_value = "3600")]
+		ttl: u64,
+	},
+
+	/// Remove an override, pinned or cached, so the name is resolved again
+	DelOverride {
+		name: String,
+	},
+
+	/// Resolve a host through each upstream nameserver individually and show
+	/// the results along with the probe outcomes recorded since startup
+	ProbeNameservers {
+		host: String,
+	},
+
+	/// Refuse to resolve a hostname until restart or `allow-hostname`
+	DenyHostname {
+		hostname: String,
+	},
+
+	/// Resolve a hostname again after it was denied
+	AllowHostname {
+		hostname: String,
+	},
+
+	/// List hostnames the resolver refuses to resolve
+	DeniedHostnames,
+
+	/// Resolve our own server name, the public self-test name and each of the
+	/// trusted servers, reporting the outcome of each
+	SelfTest,
+
+	/// Resolve servers as federation destinations through the live resolver,
+	/// caching their destinations and addresses ahead of traffic
+	Warm {
+		servers: Vec<OwnedServerName>,
+
+		/// Also warm up to this many of the servers sharing the most rooms
+		/// with us
+		#[arg(short, long)]
+		most_federated: Option<usize>,
+	},
+
+	/// Resolve the TXT records of a name through the live resolver
+	Txt {
+		name: String,
+	},
+
+	/// Show the health of each upstream nameserver and which are quarantined,
+	/// as observed by the periodic probes of `dns_quarantine_interval`
+	Quarantine,
 }
 
 #[admin_command]
@@ -51,13 +146,21 @@ async fn destinations_cache(
 async fn overrides_cache(&self, server_name: Option<String>) -> Result<RoomMessageEventContent> {
 	use service::resolver::cache::CachedOverride;
 
-	writeln!(self, "| Server Name | IP  | Port | Expires | Overriding |").await?;
-	writeln!(self, "| ----------- | --- | ----:| ------- | ---------- |").await?;
+	writeln!(self, "| Server Name | IP  | Port | Expires | Overriding | Removed At |").await?;
+	writeln!(self, "| ----------- | --- | ----:| ------- | ---------- | ---------- |").await?;
 
 	let mut overrides = self.services.resolver.cache.overrides().boxed();
 
-	while let Some((name, CachedOverride { ips, port, expire, overriding })) =
-		overrides.next().await
+	while let Some((
+		name,
+		CachedOverride {
+			ips,
+			port,
+			expire,
+			overriding,
+			expires_at,
+		},
+	)) = overrides.next().await
 	{
 		if let Some(server_name) = server_name.as_ref() {
 			if name != server_name {
@@ -66,9 +169,324 @@ async fn overrides_cache(&self, server_name: Option<String>) -> Result<RoomMessa
 		}
 
 		let expire = time::format(expire, "%+");
-		self.write_str(&format!("| {name} | {ips:?} | {port} | {expire} | {overriding:?} |\n"))
+		let expires_at = expires_at.map(|expires_at| time::format(expires_at, "%+"));
+		self.write_str(&format!(
+			"| {name} | {ips:?} | {port} | {expire} | {overriding:?} | {expires_at:?} |\n"
+		))
+		.await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+async fn bench(
+	&self,
+	host: String,
+	count: usize,
+	concurrent: bool,
+) -> Result<RoomMessageEventContent> {
+	const MAX_COUNT: usize = 1000;
+	const MAX_CONCURRENT: usize = 32;
+
+	if count == 0 {
+		return Err!("Count must be greater than zero.");
+	}
+
+	if count > MAX_COUNT {
+		return Err!("Count must not exceed {MAX_COUNT}.");
+	}
+
+	let host = host.as_str();
+	let resolver = &self.services.resolver.resolver;
+	let timed = |_| async move {
+		let timer = Instant::now();
+		let result = resolver.lookup_ips(host).await;
+		(timer.elapsed(), result.is_ok())
+	};
+
+	let results: Vec<(Duration, bool)> = if concurrent {
+		(0..count)
+			.stream()
+			.map(timed)
+			.buffer_unordered(MAX_CONCURRENT)
+			.collect()
+			.await
+	} else {
+		(0..count).stream().then(timed).collect().await
+	};
+
+	let failures = results.iter().filter(|(_, ok)| !ok).count();
+	let mut times: Vec<Duration> = results.into_iter().map(|(time, _)| time).collect();
+	times.sort_unstable();
+
+	let percentile = |pct: usize| {
+		let idx = times
+			.len()
+			.expected_sub(1)
+			.expected_mul(pct)
+			.expected_div(100);
+		times.get(idx).copied().unwrap_or_default()
+	};
+
+	let (min, median, p99, max) =
+		(percentile(0), percentile(50), percentile(99), percentile(100));
+	let out = format!(
+		"Resolved {host:?} {count} times ({failures} failed):\n\n| min | median | p99 | max \
+		 |\n| --- | ------ | --- | --- |\n| {min:?} | {median:?} | {p99:?} | {max:?} |"
+	);
+
+	Ok(RoomMessageEventContent::notice_markdown(out))
+}
+
+#[admin_command]
+async fn open_circuits(&self) -> Result<RoomMessageEventContent> {
+	writeln!(self, "| Name | Failures | Remaining Cooldown |").await?;
+	writeln!(self, "| ---- | --------:| ------------------ |").await?;
+
+	let now = Instant::now();
+	for (name, circuit) in self.services.resolver.breaker.open() {
+		let remaining = circuit
+			.open_until
+			.map(|until| until.saturating_duration_since(now))
+			.unwrap_or_default();
+
+		let failures = circuit.failures;
+		self.write_str(&format!("| {name} | {failures} | {remaining:?} |\n"))
 			.await?;
 	}
 
 	Ok(RoomMessageEventContent::notice_plain(""))
 }
+
+#[admin_command]
+async fn query_counts(&self) -> Result<RoomMessageEventContent> {
+	writeln!(self, "| Type | Queries |").await?;
+	writeln!(self, "| ---- | -------:|").await?;
+
+	for (query, count) in self.services.resolver.metrics.counts() {
+		writeln!(self, "| {query} | {count} |").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+async fn probe_nameservers(&self, host: String) -> Result<RoomMessageEventContent> {
+	let results = self
+		.services
+		.resolver
+		.resolver
+		.probe_nameservers(&host)
+		.await?;
+
+	if results.is_empty() {
+		return Err!("No upstream nameservers are configured.");
+	}
+
+	writeln!(self, "| Nameserver | Time | Result |").await?;
+	writeln!(self, "| ---------- | ---- | ------ |").await?;
+	for (nameserver, time, result) in results {
+		let result = match result {
+			| Ok(ips) => format!("{ips:?}"),
+			| Err(e) => e.to_string(),
+		};
+
+		writeln!(self, "| {nameserver} | {time:?} | {result} |").await?;
+	}
+
+	writeln!(self, "\n| Nameserver | Succeeded | Failed |").await?;
+	writeln!(self, "| ---------- | ---------:| ------:|").await?;
+	for (nameserver, probes) in self.services.resolver.metrics.probes() {
+		let (succeeded, failed) = (probes.succeeded, probes.failed);
+		writeln!(self, "| {nameserver} | {succeeded} | {failed} |").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+async fn quarantine(&self) -> Result<RoomMessageEventContent> {
+	use service::resolver::quarantine::Health;
+
+	let states = self.services.resolver.quarantine.states();
+	if states.is_empty() {
+		return Err!("No nameservers have been probed; see dns_quarantine_interval.");
+	}
+
+	writeln!(self, "| Nameserver | Latency | Consecutive Failures | Quarantined Since |").await?;
+	writeln!(self, "| ---------- | ------- | --------------------:| ----------------- |").await?;
+	for (
+		nameserver,
+		Health {
+			consecutive_failures,
+			latency,
+			quarantined_since,
+		},
+	) in states
+	{
+		let latency = latency.map_or_else(String::new, |latency| format!("{latency:?}"));
+		let since = quarantined_since.map_or_else(String::new, |since| time::format(since, "%+"));
+		writeln!(self, "| {nameserver} | {latency} | {consecutive_failures} | {since} |").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+async fn pin_override(
+	&self,
+	name: String,
+	ips: Vec<IpAddr>,
+	port: u16,
+	ttl: u64,
+) -> Result<RoomMessageEventContent> {
+	use service::resolver::cache::{CachedOverride, MAX_IPS};
+
+	if ips.len() > MAX_IPS {
+		return Err!("At most {MAX_IPS} addresses can be pinned.");
+	}
+
+	let expires_at = SystemTime::now()
+		.checked_add(Duration::from_secs(ttl))
+		.ok_or_else(|| err!("TTL is too large."))?;
+
+	self.services
+		.resolver
+		.cache
+		.set_override(&name, &CachedOverride {
+			ips: ips.into_iter().collect(),
+			port,
+			expire: expires_at,
+			overriding: None,
+			expires_at: Some(expires_at),
+		});
+
+	let expires_at = time::format(expires_at, "%+");
+	Ok(RoomMessageEventContent::notice_markdown(format!(
+		"Pinned {name} until {expires_at}."
+	)))
+}
+
+#[admin_command]
+async fn del_override(&self, name: String) -> Result<RoomMessageEventContent> {
+	self.services.resolver.cache.del_override(&name);
+
+	Ok(RoomMessageEventContent::notice_markdown(format!(
+		"Removed any override of {name}."
+	)))
+}
+
+#[admin_command]
+async fn deny_hostname(&self, hostname: String) -> Result<RoomMessageEventContent> {
+	if !self.services.resolver.denylist.add(&hostname) {
+		return Err!("{hostname} is already denied.");
+	}
+
+	Ok(RoomMessageEventContent::notice_markdown(format!(
+		"Denied resolution of {hostname}."
+	)))
+}
+
+#[admin_command]
+async fn allow_hostname(&self, hostname: String) -> Result<RoomMessageEventContent> {
+	if !self.services.resolver.denylist.remove(&hostname) {
+		return Err!("{hostname} is not denied.");
+	}
+
+	Ok(RoomMessageEventContent::notice_markdown(format!(
+		"Allowed resolution of {hostname}."
+	)))
+}
+
+#[admin_command]
+async fn denied_hostnames(&self) -> Result<RoomMessageEventContent> {
+	let names = self.services.resolver.denylist.names();
+	if names.is_empty() {
+		return Ok(RoomMessageEventContent::notice_plain("No hostnames are denied."));
+	}
+
+	for name in names {
+		writeln!(self, "{name}").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+async fn self_test(&self) -> Result<RoomMessageEventContent> {
+	use service::resolver::selftest::SelfTest;
+
+	let results = self.services.resolver.self_test_logged().await;
+	let failed = results.iter().filter(|test| test.result.is_err()).count();
+
+	writeln!(self, "| Kind | Target | Time | Result |").await?;
+	writeln!(self, "| ---- | ------ | ---- | ------ |").await?;
+	for SelfTest { kind, target, elapsed, result } in results {
+		let result = match result {
+			| Ok(resolved) => format!("pass: {resolved}"),
+			| Err(e) => format!("FAIL: {e}"),
+		};
+
+		writeln!(self, "| {kind} | {target} | {elapsed:?} | {result} |").await?;
+	}
+
+	writeln!(self, "\n{failed} failed.").await?;
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+async fn warm(
+	&self,
+	mut servers: Vec<OwnedServerName>,
+	most_federated: Option<usize>,
+) -> Result<RoomMessageEventContent> {
+	use service::resolver::warm::Warmed;
+
+	let resolver = &self.services.resolver;
+	if let Some(limit) = most_federated {
+		servers.extend(resolver.most_federated(limit).await);
+	}
+
+	servers.sort_unstable();
+	servers.dedup();
+	if servers.is_empty() {
+		return Err!("No servers to warm.");
+	}
+
+	let results = resolver.warm(servers).await;
+	let failed = results
+		.iter()
+		.filter(|warmed| warmed.result.is_err())
+		.count();
+
+	writeln!(self, "| Server | Time | Result |").await?;
+	writeln!(self, "| ------ | ---- | ------ |").await?;
+	for Warmed { server, elapsed, result } in results {
+		let result = match result {
+			| Ok(addrs) => format!("ok: {addrs}"),
+			| Err(e) => format!("FAIL: {e}"),
+		};
+
+		writeln!(self, "| {server} | {elapsed:?} | {result} |").await?;
+	}
+
+	writeln!(self, "\n{failed} failed.").await?;
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+async fn txt(&self, name: String) -> Result<RoomMessageEventContent> {
+	let records = self.services.resolver.resolver.resolve_txt(&name).await?;
+	if records.is_empty() {
+		return Ok(RoomMessageEventContent::notice_plain(format!("{name} has no TXT records.")));
+	}
+
+	for record in records {
+		writeln!(self, "{record:?}").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
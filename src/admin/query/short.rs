@@ -1,6 +1,10 @@
 use clap::Subcommand;
-use conduwuit::Result;
-use ruma::{events::room::message::RoomMessageEventContent, OwnedEventId, OwnedRoomOrAliasId};
+use conduwuit::{pdu::ShortEventId, Result};
+use futures::StreamExt;
+use ruma::{
+	events::{room::message::RoomMessageEventContent, StateEventType},
+	OwnedEventId, OwnedRoomOrAliasId,
+};
 
 use crate::{admin_command, admin_command_dispatch};
 
@@ -15,6 +19,27 @@ pub(crate) enum ShortCommand {
 	ShortRoomId {
 		room_id: OwnedRoomOrAliasId,
 	},
+
+	/// Resolve a shorteventid back to its event ID and origin_server_ts
+	EventIdFromShort {
+		shorteventid: ShortEventId,
+	},
+
+	/// List the state keys allocated for an event type with their shorts
+	ShortStateKeysByType {
+		event_type: String,
+
+		/// Number of state keys to skip
+		#[arg(long, default_value = "0")]
+		offset: usize,
+
+		/// Maximum number of state keys to list
+		#[arg(long, default_value = "100")]
+		limit: usize,
+	},
+
+	/// Entry counts and estimated memory use of the short ID caches
+	CacheStats,
 }
 
 #[admin_command]
@@ -43,3 +68,74 @@ pub(super) async fn short_room_id(
 
 	Ok(RoomMessageEventContent::notice_markdown(format!("{shortid:#?}")))
 }
+
+#[admin_command]
+pub(super) async fn event_id_from_short(
+	&self,
+	shorteventid: ShortEventId,
+) -> Result<RoomMessageEventContent> {
+	let event_id: OwnedEventId = self
+		.services
+		.rooms
+		.short
+		.get_eventid_from_short(shorteventid)
+		.await?;
+
+	let origin_server_ts = self
+		.services
+		.rooms
+		.timeline
+		.get_pdu(&event_id)
+		.await
+		.map(|pdu| pdu.origin_server_ts.to_string())
+		.unwrap_or_else(|e| format!("unavailable: {e}"));
+
+	Ok(RoomMessageEventContent::notice_markdown(format!(
+		"| Event ID | origin_server_ts |\n| -------- | ---------------- |\n| {event_id} | \
+		 {origin_server_ts} |"
+	)))
+}
+
+#[admin_command]
+pub(super) async fn short_state_keys_by_type(
+	&self,
+	event_type: String,
+	offset: usize,
+	limit: usize,
+) -> Result<RoomMessageEventContent> {
+	let event_type: StateEventType = event_type.into();
+
+	writeln!(self, "| Short | State Key |").await?;
+	writeln!(self, "| -----:| --------- |").await?;
+
+	let mut statekeys = self
+		.services
+		.rooms
+		.short
+		.iter_shortstatekeys_by_type(&event_type)
+		.skip(offset)
+		.take(limit)
+		.boxed();
+
+	while let Some((shortstatekey, state_key)) = statekeys.next().await {
+		writeln!(self, "| {shortstatekey} | {state_key:?} |").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+pub(super) async fn cache_stats(&self) -> Result<RoomMessageEventContent> {
+	use conduwuit::utils::bytes;
+	use service::rooms::short::CacheStats;
+
+	writeln!(self, "| Cache | Entries | Size |").await?;
+	writeln!(self, "| ----- | -------:| ----:|").await?;
+
+	for CacheStats { name, entries, bytes } in self.services.rooms.short.cache_stats()? {
+		let bytes = bytes::pretty(bytes);
+		writeln!(self, "| {name} | {entries} | {bytes} |").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
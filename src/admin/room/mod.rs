@@ -3,6 +3,8 @@
 mod directory;
 mod info;
 mod moderation;
+mod short;
+mod state;
 
 use clap::Subcommand;
 use conduwuit::Result;
@@ -10,7 +12,7 @@
 
 use self::{
 	alias::RoomAliasCommand, directory::RoomDirectoryCommand, info::RoomInfoCommand,
-	moderation::RoomModerationCommand,
+	moderation::RoomModerationCommand, short::RoomShortCommand, state::RoomStateCommand,
 };
 use crate::admin_command_dispatch;
 
@@ -52,6 +54,14 @@ pub(super) enum RoomCommand {
 	/// - Manage the room directory
 	Directory(RoomDirectoryCommand),
 
+	#[command(subcommand)]
+	/// - Manage rooms' short IDs
+	Short(RoomShortCommand),
+
+	#[command(subcommand)]
+	/// - Inspect rooms' state snapshots
+	State(RoomStateCommand),
+
 	/// - Check if we know about a room
 	Exists {
 		room_id: OwnedRoomId,
@@ -0,0 +1,183 @@
+use clap::Subcommand;
+use conduwuit::{utils::time, Result};
+use ruma::{events::room::message::RoomMessageEventContent, OwnedRoomId, RoomId};
+use service::rooms::short::{IntegrityScan, Repair};
+
+use crate::{admin_command, admin_command_dispatch};
+
+#[admin_command_dispatch]
+#[derive(Debug, Subcommand)]
+pub(crate) enum RoomShortCommand {
+	/// - Allocate the shortroomid of a room ahead of any of its events
+	///
+	/// Prints the existing shortroomid if the room already has one.
+	AllocRoomid {
+		room_id: OwnedRoomId,
+	},
+
+	/// - Raise the global counter above the highest allocated short ID
+	///
+	/// Recovers from a restore or manual edit which left the counter behind the
+	/// short ID tables, where new allocations would collide with existing ones.
+	/// Does nothing if the counter is already ahead; safe to run anytime.
+	ReconcileCount,
+
+	/// - Allocate the shortroomids of a space and every room within it
+	///
+	/// Rooms are found through the m.space.child events of the space and of
+	/// its subspaces, as far as the server has their state. Allocating ahead
+	/// avoids the latency of doing so one room at a time later.
+	AllocSpace {
+		space_id: OwnedRoomId,
+	},
+
+	/// - Show the global counter short IDs are allocated from
+	///
+	/// Reading the counter does not advance it. Sampled over time this shows
+	/// the rate at which IDs are consumed.
+	NextCount,
+
+	/// - Sample the short ID tables for entries whose reverse mapping lacks a
+	///   matching forward mapping
+	ScanIntegrity {
+		/// Entries to sample from each table
+		#[arg(long, default_value = "1000")]
+		sample: usize,
+	},
+
+	/// - Show the result of the most recent short ID integrity scan
+	LastIntegrityScan,
+
+	/// - Repair short ID mappings orphaned by an interrupted write
+	///
+	/// Scans every entry of the short ID tables: a mapping missing its
+	/// forward or reverse side is recreated from the other, and a reverse
+	/// entry superseded by another for the same key is removed. Mappings which
+	/// contradict each other are only reported. Nothing is changed without
+	/// --apply. Scanning the event tables of a large database takes a while.
+	Repair {
+		/// Write the fixes rather than only reporting them
+		#[arg(long)]
+		apply: bool,
+	},
+}
+
+#[admin_command]
+async fn alloc_roomid(&self, room_id: OwnedRoomId) -> Result<RoomMessageEventContent> {
+	let short = &self.services.rooms.short;
+	if let Ok(shortroomid) = short.get_shortroomid(&room_id).await {
+		return Ok(RoomMessageEventContent::notice_markdown(format!(
+			"{room_id} already has shortroomid {shortroomid}."
+		)));
+	}
+
+	let shortroomid = short.get_or_create_shortroomid(&room_id).await?;
+
+	Ok(RoomMessageEventContent::notice_markdown(format!(
+		"Allocated shortroomid {shortroomid} for {room_id}."
+	)))
+}
+
+#[admin_command]
+async fn alloc_space(&self, space_id: OwnedRoomId) -> Result<RoomMessageEventContent> {
+	let short = &self.services.rooms.short;
+	let rooms = self
+		.services
+		.rooms
+		.spaces
+		.get_local_descendants(&space_id)
+		.await?;
+
+	let mut existing = 0_usize;
+	for room_id in &rooms {
+		if short.get_shortroomid(room_id).await.is_ok() {
+			existing = existing.saturating_add(1);
+		}
+	}
+
+	let room_ids: Vec<&RoomId> = rooms.iter().map(AsRef::as_ref).collect();
+	short.multi_get_or_create_shortroomid(&room_ids).await?;
+
+	let allocated = rooms.len().saturating_sub(existing);
+	Ok(RoomMessageEventContent::notice_markdown(format!(
+		"Found {} rooms in {space_id}: allocated {allocated} shortroomids, {existing} already \
+		 had one.",
+		rooms.len()
+	)))
+}
+
+#[admin_command]
+async fn reconcile_count(&self) -> Result<RoomMessageEventContent> {
+	let max = self.services.rooms.short.max_allocated_short().await?;
+	let prior = self.services.globals.raise_count(max);
+
+	let out = if prior < max {
+		format!("Raised the counter from {prior} to {max}, the highest allocated short ID.")
+	} else {
+		format!(
+			"The counter ({prior}) is already at or above the highest allocated short ID \
+			 ({max})."
+		)
+	};
+
+	Ok(RoomMessageEventContent::notice_markdown(out))
+}
+
+#[admin_command]
+async fn next_count(&self) -> Result<RoomMessageEventContent> {
+	let current = self.services.globals.current_count()?;
+	let next = current.saturating_add(1);
+	let headroom = self.services.globals.counter_headroom();
+
+	Ok(RoomMessageEventContent::notice_markdown(format!(
+		"The counter is at {current}; the next short ID allocated will be {next}. {headroom} \
+		 counts remain before allocation is refused."
+	)))
+}
+
+#[admin_command]
+async fn scan_integrity(&self, sample: usize) -> Result<RoomMessageEventContent> {
+	let scan = self.services.rooms.short.scan_integrity(sample).await?;
+
+	Ok(RoomMessageEventContent::notice_markdown(format_scan(&scan)))
+}
+
+#[admin_command]
+async fn last_integrity_scan(&self) -> Result<RoomMessageEventContent> {
+	let out = self.services.rooms.short.last_integrity_scan().map_or_else(
+		|| "No integrity scan has run since startup.".to_owned(),
+		|scan| format_scan(&scan),
+	);
+
+	Ok(RoomMessageEventContent::notice_markdown(out))
+}
+
+#[admin_command]
+async fn repair(&self, apply: bool) -> Result<RoomMessageEventContent> {
+	let Repair {
+		applied,
+		checked,
+		forward_recreated,
+		reverse_recreated,
+		reverse_removed,
+		conflicts,
+	} = self.services.rooms.short.repair(apply).await?;
+
+	let verb = if applied { "Fixed" } else { "Would fix" };
+	let mut out = format!(
+		"Checked {checked} entries. {verb}: {forward_recreated} missing forward mappings, \
+		 {reverse_recreated} missing reverse mappings, {reverse_removed} superseded reverse \
+		 entries. {conflicts} conflicts need manual attention; see the server log."
+	);
+
+	if !applied {
+		out.push_str("\n\nThis was a dry run; rerun with --apply to write the fixes.");
+	}
+
+	Ok(RoomMessageEventContent::notice_markdown(out))
+}
+
+fn format_scan(IntegrityScan { at, checked, mismatches }: &IntegrityScan) -> String {
+	let at = time::format(*at, "%+");
+	format!("Checked {checked} entries at {at}: {mismatches} mismatches.")
+}
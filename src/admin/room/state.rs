@@ -0,0 +1,102 @@
+use std::fmt::Write;
+
+use clap::Subcommand;
+use conduwuit::{utils::IterStream, Err, Result};
+use futures::{StreamExt, TryStreamExt};
+use ruma::{events::room::message::RoomMessageEventContent, OwnedEventId, OwnedRoomId};
+use service::rooms::short::ShortStateHash;
+
+use crate::{admin_command, admin_command_dispatch, PAGE_SIZE};
+
+#[admin_command_dispatch]
+#[derive(Debug, Subcommand)]
+pub(crate) enum RoomStateCommand {
+	/// - List the entries of a state snapshot
+	///
+	/// Each (shortstatekey, shorteventid) entry of the snapshot is shown with
+	/// the event type, state key and event ID it stands for, to help diagnose
+	/// incorrect state.
+	Dump {
+		room_id: OwnedRoomId,
+
+		shortstatehash: ShortStateHash,
+
+		page: Option<usize>,
+	},
+}
+
+#[admin_command]
+async fn dump(
+	&self,
+	room_id: OwnedRoomId,
+	shortstatehash: ShortStateHash,
+	page: Option<usize>,
+) -> Result<RoomMessageEventContent> {
+	let page = page.unwrap_or(1);
+	let services = self.services;
+	if services
+		.rooms
+		.short
+		.get_shortroomid(&room_id)
+		.await
+		.is_err()
+	{
+		return Err!("{room_id} is not known to this server.");
+	}
+
+	let current = services
+		.rooms
+		.state
+		.get_room_shortstatehash(&room_id)
+		.await
+		.is_ok_and(|current| current == shortstatehash);
+
+	let entries: Vec<_> = services
+		.rooms
+		.state_accessor
+		.state_full_shortids(shortstatehash)
+		.try_collect()
+		.await?;
+
+	let total = entries.len();
+	let entries: Vec<_> = entries
+		.into_iter()
+		.skip(page.saturating_sub(1).saturating_mul(PAGE_SIZE))
+		.take(PAGE_SIZE)
+		.collect();
+
+	if entries.is_empty() {
+		return Ok(RoomMessageEventContent::notice_plain("No more state entries."));
+	}
+
+	let statekeys = services
+		.rooms
+		.short
+		.multi_get_statekey_from_short(entries.iter().map(|(key, _)| *key).stream());
+
+	let event_ids = services
+		.rooms
+		.short
+		.multi_get_eventid_from_short::<OwnedEventId, _>(
+			entries.iter().map(|(_, event)| *event).stream(),
+		);
+
+	let resolved: Vec<_> = statekeys.zip(event_ids).collect().await;
+
+	let mut out = format!(
+		"State snapshot {shortstatehash} of {room_id} ({}), {total} entries, page {page}:\n```\n",
+		if current { "current" } else { "not current" },
+	);
+
+	for ((shortstatekey, shorteventid), (statekey, event_id)) in entries.iter().zip(resolved) {
+		let statekey =
+			statekey.map_or_else(|e| format!("<{e}>"), |(kind, key)| format!("{kind} {key:?}"));
+		let event_id = event_id.map_or_else(|e| format!("<{e}>"), |id| id.to_string());
+
+		writeln!(out, "{shortstatekey}\t{shorteventid}\t{statekey}\t{event_id}")?;
+	}
+
+	out.push_str("```");
+
+	Ok(RoomMessageEventContent::notice_markdown(out))
+}
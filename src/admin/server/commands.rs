@@ -1,7 +1,7 @@
 use std::{fmt::Write, path::PathBuf, sync::Arc};
 
 use conduwuit::{info, utils::time, warn, Err, Result};
-use ruma::events::room::message::RoomMessageEventContent;
+use ruma::{events::room::message::RoomMessageEventContent, OwnedServerName};
 
 use crate::admin_command;
 
@@ -38,6 +38,113 @@ pub(super) async fn reload_config(
 	Ok(RoomMessageEventContent::text_plain("Successfully reconfigured."))
 }
 
+#[admin_command]
+pub(super) async fn set_well_known_client(&self, url: String) -> Result<RoomMessageEventContent> {
+	self.services.config.set_well_known_client(&url)?;
+
+	Ok(RoomMessageEventContent::text_plain(format!(
+		"Client well-known set to {url}. This is not persisted to the config file."
+	)))
+}
+
+#[admin_command]
+pub(super) async fn set_well_known_server(
+	&self,
+	server: OwnedServerName,
+) -> Result<RoomMessageEventContent> {
+	self.services.config.set_well_known_server(server.clone())?;
+
+	Ok(RoomMessageEventContent::text_plain(format!(
+		"Server well-known set to {server}. This is not persisted to the config file."
+	)))
+}
+
+#[admin_command]
+pub(super) async fn versions(&self) -> Result<RoomMessageEventContent> {
+	use api::client::{client_versions, unstable_feature_msc};
+
+	if let Some(reason) = self.services.globals.maintenance_mode() {
+		writeln!(self, "Maintenance mode is active; clients get a 503 instead: {reason}\n")
+			.await?;
+	}
+
+	let response = client_versions(&self.services.server.config, None, true);
+	let versions = response.versions.join(", ");
+	writeln!(self, "Versions: {versions}\n").await?;
+
+	writeln!(self, "| Unstable Feature | Enabled | MSC |").await?;
+	writeln!(self, "| ---------------- | ------- | --- |").await?;
+	for (feature, enabled) in &response.unstable_features {
+		let msc = unstable_feature_msc(feature).unwrap_or("");
+		writeln!(self, "| {feature} | {enabled} | {msc} |").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
+#[admin_command]
+pub(super) async fn versions_preview(
+	&self,
+	option: String,
+	enabled: bool,
+	user_agent: Option<String>,
+	unauthenticated: bool,
+) -> Result<RoomMessageEventContent> {
+	use api::client::client_versions;
+
+	let current = &*self.services.server.config;
+	let mut preview = current.clone();
+	match option.as_str() {
+		| "unstable_features_require_auth" => preview.unstable_features_require_auth = enabled,
+		| _ => return Err!("{option} does not affect /_matrix/client/versions."),
+	}
+
+	let user_agent = user_agent.as_deref();
+	let before = client_versions(current, user_agent, !unauthenticated);
+	let after = client_versions(&preview, user_agent, !unauthenticated);
+
+	let removed = before
+		.versions
+		.iter()
+		.filter(|version| !after.versions.contains(version))
+		.chain(
+			before
+				.unstable_features
+				.keys()
+				.filter(|feature| !after.unstable_features.contains_key(*feature)),
+		);
+
+	let added = after
+		.versions
+		.iter()
+		.filter(|version| !before.versions.contains(version))
+		.chain(
+			after
+				.unstable_features
+				.keys()
+				.filter(|feature| !before.unstable_features.contains_key(*feature)),
+		);
+
+	let state = if enabled { "on" } else { "off" };
+	let mut changed = false;
+	writeln!(self, "With {option} {state}:").await?;
+	for item in removed {
+		changed = true;
+		writeln!(self, "- {item}").await?;
+	}
+
+	for item in added {
+		changed = true;
+		writeln!(self, "+ {item}").await?;
+	}
+
+	if !changed {
+		writeln!(self, "No change to the response.").await?;
+	}
+
+	Ok(RoomMessageEventContent::notice_plain(""))
+}
+
 #[admin_command]
 pub(super) async fn list_features(
 	&self,
@@ -71,6 +178,32 @@ pub(super) async fn list_features(
 	Ok(RoomMessageEventContent::text_markdown(features))
 }
 
+#[admin_command]
+pub(super) async fn maintenance(&self, reason: Vec<String>) -> Result<RoomMessageEventContent> {
+	let reason = if reason.is_empty() {
+		"no reason given".to_owned()
+	} else {
+		reason.join(" ")
+	};
+
+	self.services.globals.set_maintenance_mode(Some(reason));
+
+	Ok(RoomMessageEventContent::text_plain(
+		"Maintenance mode enabled. This is not persisted across restarts.",
+	))
+}
+
+#[admin_command]
+pub(super) async fn end_maintenance(&self) -> Result<RoomMessageEventContent> {
+	if self.services.globals.maintenance_mode().is_none() {
+		return Err!("The server is not in maintenance mode.");
+	}
+
+	self.services.globals.set_maintenance_mode(None);
+
+	Ok(RoomMessageEventContent::text_plain("Maintenance mode disabled."))
+}
+
 #[admin_command]
 pub(super) async fn memory_usage(&self) -> Result<RoomMessageEventContent> {
 	let services_usage = self.services.memory_usage().await?;
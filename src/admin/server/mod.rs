@@ -2,8 +2,9 @@
 
 use std::path::PathBuf;
 
-use clap::Subcommand;
+use clap::{builder::BoolishValueParser, ArgAction, Subcommand};
 use conduwuit::Result;
+use ruma::OwnedServerName;
 
 use crate::admin_command_dispatch;
 
@@ -21,6 +22,16 @@ pub(super) enum ServerCommand {
 		path: Option<PathBuf>,
 	},
 
+	/// - Set the client well-known URL until the next config reload
+	SetWellKnownClient {
+		url: String,
+	},
+
+	/// - Set the server well-known name until the next config reload
+	SetWellKnownServer {
+		server: OwnedServerName,
+	},
+
 	/// - List the features built into the server
 	ListFeatures {
 		#[arg(short, long)]
@@ -33,6 +44,45 @@ pub(super) enum ServerCommand {
 		comma: bool,
 	},
 
+	/// - Show the spec versions and unstable features advertised by
+	///   /_matrix/client/versions
+	///
+	/// Shows what an authenticated client without a tailored user agent gets
+	/// under the current config, and whether maintenance mode is refusing the
+	/// request instead.
+	Versions,
+
+	/// - Preview how /_matrix/client/versions would change were a config option
+	///   toggled, without changing the live config
+	///
+	/// Only options affecting the response are accepted; currently
+	/// `unstable_features_require_auth`. Prints the features and versions
+	/// which would be added or removed for the described client.
+	VersionsPreview {
+		option: String,
+
+		/// The value to preview: on or off
+		#[arg(action = ArgAction::Set, value_parser = BoolishValueParser::new())]
+		enabled: bool,
+
+		/// Preview for a client sending this User-Agent
+		#[arg(long)]
+		user_agent: Option<String>,
+
+		/// Preview for a client which does not authenticate
+		#[arg(long)]
+		unauthenticated: bool,
+	},
+
+	/// - Enter maintenance mode: /_matrix/client/versions fails with a 503 and
+	///   Retry-After so that clients back off, until `end-maintenance`
+	Maintenance {
+		reason: Vec<String>,
+	},
+
+	/// - Leave maintenance mode
+	EndMaintenance,
+
 	/// - Print database memory usage statistics
 	MemoryUsage,
 
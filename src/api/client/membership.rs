@@ -16,7 +16,7 @@
 	utils::{self, shuffle, IterStream, ReadyExt},
 	warn, Err, PduEvent, Result,
 };
-use futures::{join, FutureExt, StreamExt, TryFutureExt};
+use futures::{join, FutureExt, StreamExt, TryFutureExt, TryStreamExt};
 use ruma::{
 	api::{
 		client::{
@@ -47,6 +47,7 @@
 	appservice::RegistrationInfo,
 	pdu::gen_event_id,
 	rooms::{
+		short::ShortStateKey,
 		state::RoomMutexGuard,
 		state_compressor::{CompressedState, HashSetCompressStateEvent},
 	},
@@ -1075,7 +1076,7 @@ async fn join_room_by_id_helper_remote(
 		.rooms
 		.short
 		.get_or_create_shortroomid(room_id)
-		.await;
+		.await?;
 
 	info!("Parsing join event");
 	let parsed_join_pdu = PduEvent::from_id_val(&event_id, join_event.clone())
@@ -1103,12 +1104,13 @@ async fn join_room_by_id_helper_remote(
 				.validate_and_add_event_id_no_fetch(pdu, &room_version_id)
 		})
 		.ready_filter_map(Result::ok)
-		.fold(HashMap::new(), |mut state, (event_id, value)| async move {
+		.map(Ok::<_, conduwuit::Error>)
+		.try_fold(HashMap::new(), |mut state, (event_id, value)| async move {
 			let pdu = match PduEvent::from_id_val(&event_id, value.clone()) {
 				| Ok(pdu) => pdu,
 				| Err(e) => {
 					debug_warn!("Invalid PDU in send_join response: {e:?}: {value:#?}");
-					return state;
+					return Ok(state);
 				},
 			};
 
@@ -1118,14 +1120,14 @@ async fn join_room_by_id_helper_remote(
 					.rooms
 					.short
 					.get_or_create_shortstatekey(&pdu.kind.to_string().into(), state_key)
-					.await;
+					.await?;
 
 				state.insert(shortstatekey, pdu.event_id.clone());
 			}
 
-			state
+			Ok(state)
 		})
-		.await;
+		.await?;
 
 	drop(cork);
 
@@ -1176,8 +1178,8 @@ async fn join_room_by_id_helper_remote(
 		.rooms
 		.state_compressor
 		.compress_state_events(state.iter().map(|(ssk, eid)| (ssk, eid.borrow())))
-		.collect()
-		.await;
+		.try_collect()
+		.await?;
 
 	debug!("Saving compressed state");
 	let HashSetCompressStateEvent {
@@ -2168,7 +2170,7 @@ async fn knock_room_helper_local(
 		.rooms
 		.short
 		.get_or_create_shortroomid(room_id)
-		.await;
+		.await?;
 
 	info!("Parsing knock event");
 
@@ -2296,7 +2298,7 @@ async fn knock_room_helper_remote(
 		.rooms
 		.short
 		.get_or_create_shortroomid(room_id)
-		.await;
+		.await?;
 
 	info!("Parsing knock event");
 	let parsed_knock_pdu = PduEvent::from_id_val(&event_id, knock_event.clone())
@@ -2309,7 +2311,7 @@ async fn knock_room_helper_remote(
 		.map(|event| serde_json::from_str::<CanonicalJsonObject>(event.clone().into_json().get()))
 		.filter_map(Result::ok);
 
-	let mut state_map: HashMap<u64, OwnedEventId> = HashMap::new();
+	let mut state_map: HashMap<ShortStateKey, OwnedEventId> = HashMap::new();
 
 	for event in state {
 		let Some(state_key) = event.get("state_key") else {
@@ -2336,7 +2338,7 @@ async fn knock_room_helper_remote(
 			.rooms
 			.short
 			.get_or_create_shortstatekey(&event_type, &state_key)
-			.await;
+			.await?;
 
 		services.rooms.outlier.add_pdu_outlier(&event_id, &event);
 		state_map.insert(shortstatekey, event_id.clone());
@@ -2347,8 +2349,8 @@ async fn knock_room_helper_remote(
 		.rooms
 		.state_compressor
 		.compress_state_events(state_map.iter().map(|(ssk, eid)| (ssk, eid.borrow())))
-		.collect()
-		.await;
+		.try_collect()
+		.await?;
 
 	debug!("Saving compressed state");
 	let HashSetCompressStateEvent {
@@ -60,6 +60,7 @@
 pub(super) use presence::*;
 pub(super) use profile::*;
 pub use profile::{update_all_rooms, update_avatar_url, update_displayname};
+pub use unversioned::{client_versions, supported_versions, unstable_feature_msc};
 pub(super) use push::*;
 pub(super) use read_marker::*;
 pub(super) use redact::*;
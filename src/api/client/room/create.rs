@@ -111,7 +111,7 @@ pub(crate) async fn create_room_route(
 		.rooms
 		.short
 		.get_or_create_shortroomid(&room_id)
-		.await;
+		.await?;
 	let state_lock = services.rooms.state.mutex.lock(&room_id).await;
 
 	let alias: Option<OwnedRoomAliasId> = if let Some(alias) = body.room_alias_name.as_ref() {
@@ -66,7 +66,7 @@ pub(crate) async fn upgrade_room_route(
 		.rooms
 		.short
 		.get_or_create_shortroomid(&replacement_room)
-		.await;
+		.await?;
 
 	let state_lock = services.rooms.state.mutex.lock(&body.room_id).await;
 
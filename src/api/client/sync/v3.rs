@@ -510,11 +510,15 @@ async fn handle_left_room(
 		.collect()
 		.await;
 
-	let leave_shortstatekey = services
+	let Ok(leave_shortstatekey) = services
 		.rooms
 		.short
-		.get_or_create_shortstatekey(&StateEventType::RoomMember, sender_user.as_str())
-		.await;
+		.get_shortstatekey(&StateEventType::RoomMember, sender_user.as_str())
+		.await
+	else {
+		error!(event_id = %left_event_id, "Leave event has no shortstatekey");
+		return Ok(None);
+	};
 
 	left_state_ids.insert(leave_shortstatekey, left_event_id);
 
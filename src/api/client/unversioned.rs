@@ -1,11 +1,146 @@
-use std::collections::BTreeMap;
+use std::{
+	collections::BTreeMap,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{
+	extract::{Query, State},
+	response::{IntoResponse, Response},
+	Json,
+};
+use axum_extra::{
+	headers::{authorization::Bearer, Authorization, UserAgent},
+	TypedHeader,
+};
+use conduwuit::{warn, Config, Err, Error, Server};
 use futures::StreamExt;
-use ruma::api::client::discovery::get_supported_versions;
+use http::{
+	header::{ACCEPT, CONTENT_TYPE},
+	HeaderMap,
+};
+use ruma::{
+	api::client::{
+		discovery::get_supported_versions, error::ErrorKind, room::create_room::v3::RoomPreset,
+	},
+	OwnedRoomId, OwnedUserId,
+};
+use serde::Deserialize;
+use service::{
+	resolver::{cache::CachedDest, fed::FedDest},
+	Services,
+};
 
 use crate::{Result, Ruma};
 
+/// An unstable feature advertised by `/versions`.
+#[derive(Debug)]
+pub struct UnstableFeature {
+	/// Name of the feature as advertised.
+	pub name: &'static str,
+
+	/// The MSC the feature implements, if there is one.
+	pub msc: Option<&'static str>,
+
+	/// What the feature is, briefly.
+	pub description: &'static str,
+
+	/// Link to the spec proposal defining the feature, if there is one.
+	pub url: Option<&'static str>,
+}
+
+/// Content type of the Prometheus text exposition format.
+const CONTENT_TYPE_PROMETHEUS: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Unstable features advertised by `/versions`.
+const UNSTABLE_FEATURES: &[UnstableFeature] = &[
+	UnstableFeature {
+		name: "org.matrix.e2e_cross_signing",
+		msc: None,
+		description: "cross-signing",
+		url: None,
+	},
+	UnstableFeature {
+		name: "org.matrix.msc2285.stable",
+		msc: Some("MSC2285"),
+		description: "private read receipts",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/2285"),
+	},
+	UnstableFeature {
+		name: "uk.half-shot.msc2666.query_mutual_rooms",
+		msc: Some("MSC2666"),
+		description: "query mutual rooms",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/2666"),
+	},
+	UnstableFeature {
+		name: "org.matrix.msc2836",
+		msc: Some("MSC2836"),
+		description: "threading/threads",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/2836"),
+	},
+	UnstableFeature {
+		name: "org.matrix.msc2946",
+		msc: Some("MSC2946"),
+		description: "spaces/hierarchy summaries",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/2946"),
+	},
+	UnstableFeature {
+		name: "org.matrix.msc3026.busy_presence",
+		msc: Some("MSC3026"),
+		description: "busy presence status",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/3026"),
+	},
+	UnstableFeature {
+		name: "org.matrix.msc3827",
+		msc: Some("MSC3827"),
+		description: "filtering of /publicRooms by room type",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/3827"),
+	},
+	UnstableFeature {
+		name: "org.matrix.msc3952_intentional_mentions",
+		msc: Some("MSC3952"),
+		description: "intentional mentions",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/3952"),
+	},
+	UnstableFeature {
+		name: "org.matrix.msc3575",
+		msc: Some("MSC3575"),
+		description: "sliding sync",
+		url: Some(
+			"https://github.com/matrix-org/matrix-spec-proposals/pull/3575/files#r1588877046",
+		),
+	},
+	UnstableFeature {
+		name: "org.matrix.msc3916.stable",
+		msc: Some("MSC3916"),
+		description: "authenticated media",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/3916"),
+	},
+	UnstableFeature {
+		name: "org.matrix.msc4180",
+		msc: Some("MSC4180"),
+		description: "stable flag for 3916",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/4180"),
+	},
+	UnstableFeature {
+		name: "uk.tcpip.msc4133",
+		msc: Some("MSC4133"),
+		description: "Extending User Profile API with Key:Value Pairs",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/4133"),
+	},
+	UnstableFeature {
+		name: "us.cloke.msc4175",
+		msc: Some("MSC4175"),
+		description: "Profile field for user time zone",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/4175"),
+	},
+	UnstableFeature {
+		name: "org.matrix.simplified_msc3575",
+		msc: Some("MSC4186"),
+		description: "Simplified Sliding sync",
+		url: Some("https://github.com/matrix-org/matrix-spec-proposals/pull/4186"),
+	},
+];
+
 /// # `GET /_matrix/client/versions`
 ///
 /// Get the versions of the specification and unstable features supported by
@@ -18,10 +153,70 @@
 ///
 /// Note: Unstable features are used while developing new features. Clients
 /// should avoid using unstable features in their stable releases
+///
+/// While the server is in maintenance mode this fails with a 503
+/// `M_UNKNOWN` and a Retry-After header, so clients back off.
 pub(crate) async fn get_supported_versions_route(
-	_body: Ruma<get_supported_versions::Request>,
+	State(services): State<crate::State>,
+	user_agent: Option<TypedHeader<UserAgent>>,
+	body: Ruma<get_supported_versions::Request>,
 ) -> Result<get_supported_versions::Response> {
-	let resp = get_supported_versions::Response {
+	const RETRY_AFTER: Duration = Duration::from_secs(60);
+
+	if let Some(reason) = services.globals.maintenance_mode() {
+		return Err(Error::Unavailable(
+			format!("Server is undergoing maintenance: {reason}").into(),
+			RETRY_AFTER,
+		));
+	}
+
+	let user_agent = user_agent
+		.as_ref()
+		.map(|TypedHeader(user_agent)| user_agent.as_str());
+	let authenticated = body.sender_user.is_some() || body.appservice_info.is_some();
+
+	Ok(client_versions(&services.server.config, user_agent, authenticated))
+}
+
+/// The response of `/_matrix/client/versions` as tailored to a client by its
+/// user agent and whether it authenticated. A pure function of the given
+/// config, so the effect of a config change can be previewed.
+#[must_use]
+pub fn client_versions(
+	config: &Config,
+	user_agent: Option<&str>,
+	authenticated: bool,
+) -> get_supported_versions::Response {
+	let mut response = supported_versions();
+	if let Some((_, features)) = user_agent.and_then(|ua| user_agent_profile(config, ua)) {
+		response
+			.unstable_features
+			.retain(|feature, _| features.contains(feature));
+	}
+
+	if config.unstable_features_require_auth && !authenticated {
+		response.unstable_features.clear();
+	}
+
+	response
+}
+
+/// The entry of `unstable_features_by_user_agent` restricting the unstable
+/// features advertised to a client, if any.
+fn user_agent_profile<'a>(
+	config: &'a Config,
+	user_agent: &str,
+) -> Option<(&'a String, &'a Vec<String>)> {
+	config
+		.unstable_features_by_user_agent
+		.iter()
+		.find(|(pattern, _)| user_agent.contains(pattern.as_str()))
+}
+
+/// The response of `/_matrix/client/versions`.
+#[must_use]
+pub fn supported_versions() -> get_supported_versions::Response {
+	get_supported_versions::Response {
 		versions: vec![
 			"r0.0.1".to_owned(),
 			"r0.1.0".to_owned(),
@@ -38,25 +233,21 @@ pub(crate) async fn get_supported_versions_route(
 			"v1.5".to_owned(),
 			"v1.11".to_owned(),
 		],
-		unstable_features: BTreeMap::from_iter([
-			("org.matrix.e2e_cross_signing".to_owned(), true),
-			("org.matrix.msc2285.stable".to_owned(), true), /* private read receipts (https://github.com/matrix-org/matrix-spec-proposals/pull/2285) */
-			("uk.half-shot.msc2666.query_mutual_rooms".to_owned(), true), /* query mutual rooms (https://github.com/matrix-org/matrix-spec-proposals/pull/2666) */
-			("org.matrix.msc2836".to_owned(), true), /* threading/threads (https://github.com/matrix-org/matrix-spec-proposals/pull/2836) */
-			("org.matrix.msc2946".to_owned(), true), /* spaces/hierarchy summaries (https://github.com/matrix-org/matrix-spec-proposals/pull/2946) */
-			("org.matrix.msc3026.busy_presence".to_owned(), true), /* busy presence status (https://github.com/matrix-org/matrix-spec-proposals/pull/3026) */
-			("org.matrix.msc3827".to_owned(), true), /* filtering of /publicRooms by room type (https://github.com/matrix-org/matrix-spec-proposals/pull/3827) */
-			("org.matrix.msc3952_intentional_mentions".to_owned(), true), /* intentional mentions (https://github.com/matrix-org/matrix-spec-proposals/pull/3952) */
-			("org.matrix.msc3575".to_owned(), true), /* sliding sync (https://github.com/matrix-org/matrix-spec-proposals/pull/3575/files#r1588877046) */
-			("org.matrix.msc3916.stable".to_owned(), true), /* authenticated media (https://github.com/matrix-org/matrix-spec-proposals/pull/3916) */
-			("org.matrix.msc4180".to_owned(), true), /* stable flag for 3916 (https://github.com/matrix-org/matrix-spec-proposals/pull/4180) */
-			("uk.tcpip.msc4133".to_owned(), true), /* Extending User Profile API with Key:Value Pairs (https://github.com/matrix-org/matrix-spec-proposals/pull/4133) */
-			("us.cloke.msc4175".to_owned(), true), /* Profile field for user time zone (https://github.com/matrix-org/matrix-spec-proposals/pull/4175) */
-			("org.matrix.simplified_msc3575".to_owned(), true), /* Simplified Sliding sync (https://github.com/matrix-org/matrix-spec-proposals/pull/4186) */
-		]),
-	};
+		unstable_features: UNSTABLE_FEATURES
+			.iter()
+			.map(|feature| (feature.name.to_owned(), true))
+			.collect(),
+	}
+}
 
-	Ok(resp)
+/// The MSC implemented by an unstable feature advertised by `/versions`, if
+/// there is one.
+#[must_use]
+pub fn unstable_feature_msc(feature: &str) -> Option<&'static str> {
+	UNSTABLE_FEATURES
+		.iter()
+		.find(|unstable| unstable.name == feature)
+		.and_then(|unstable| unstable.msc)
 }
 
 /// # `GET /_conduwuit/server_version`
@@ -70,17 +261,461 @@ pub(crate) async fn conduwuit_server_version() -> Result<impl IntoResponse> {
 	})))
 }
 
+/// # `GET /_conduwuit/server_info`
+///
+/// conduwuit-specific API to get the server version along with when it was
+/// started and its uptime in seconds, e.g. for monitoring restarts. Served in
+/// the Prometheus text format when the client accepts `text/plain`.
+pub(crate) async fn conduwuit_server_info(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+) -> Result<Response> {
+	let started = services.server.started;
+	let started_ts = started
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs();
+
+	let uptime = started.elapsed().unwrap_or_default().as_secs();
+
+	if accepts_prometheus(&headers) {
+		return Ok(prometheus_gauges(&[
+			(
+				"conduwuit_start_time_seconds",
+				"Unix time at which the server was started.",
+				started_ts,
+			),
+			("conduwuit_uptime_seconds", "Seconds since the server was started.", uptime),
+		]));
+	}
+
+	Ok(Json(serde_json::json!({
+		"name": conduwuit::version::name(),
+		"version": conduwuit::version::version(),
+		"started_ts": started_ts,
+		"uptime_secs": uptime,
+	}))
+	.into_response())
+}
+
 /// # `GET /_conduwuit/local_user_count`
 ///
 /// conduwuit-specific API to return the amount of users registered on this
 /// homeserver. Endpoint is disabled if federation is disabled for privacy. This
-/// only includes active users (not deactivated, no guests, etc)
+/// only includes active users (not deactivated, no guests, etc). Served in the
+/// Prometheus text format when the client accepts `text/plain`.
+///
+/// Should counting fail on a database error, the last count is served instead
+/// and marked `stale`, keeping dashboards working through transient issues.
 pub(crate) async fn conduwuit_local_user_count(
 	State(services): State<crate::State>,
+	headers: HeaderMap,
+) -> Result<Response> {
+	let (user_count, stale) = match services.users.count_local_users().await {
+		| Ok(user_count) => (user_count, false),
+		| Err(e) => {
+			let Some(user_count) = services.users.last_local_user_count() else {
+				return Err(e);
+			};
+
+			warn!("Failed to count local users, serving the last count: {e}");
+			(user_count, true)
+		},
+	};
+
+	if accepts_prometheus(&headers) {
+		return Ok(prometheus_gauges(&[
+			(
+				"conduwuit_local_users_total",
+				"Active users registered on this homeserver.",
+				user_count.try_into()?,
+			),
+			(
+				"conduwuit_local_users_stale",
+				"Whether the user count is the last known one, as counting failed.",
+				stale.into(),
+			),
+		]));
+	}
+
+	Ok(Json(serde_json::json!({
+		"count": user_count,
+		"stale": stale,
+	}))
+	.into_response())
+}
+
+/// Whether the client accepts the Prometheus text exposition format, as
+/// Prometheus does when scraping, rather than JSON.
+fn accepts_prometheus(headers: &HeaderMap) -> bool {
+	headers
+		.get_all(ACCEPT)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','))
+		.any(|media| media.trim().starts_with("text/plain"))
+}
+
+/// Renders each of the given name, help text and value as a gauge in the
+/// Prometheus text exposition format.
+fn prometheus_gauges(gauges: &[(&str, &str, u64)]) -> Response {
+	let body: String = gauges
+		.iter()
+		.map(|(name, help, value)| {
+			format!("# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n")
+		})
+		.collect();
+
+	([(CONTENT_TYPE, CONTENT_TYPE_PROMETHEUS)], body).into_response()
+}
+
+/// Renders a gauge with one sample for each of the given label values in the
+/// Prometheus text exposition format.
+fn prometheus_labeled_gauge(
+	name: &str,
+	help: &str,
+	label: &str,
+	samples: &[(&str, u64)],
+) -> Response {
+	let samples: String = samples
+		.iter()
+		.map(|(value, sample)| format!("{name}{{{label}=\"{value}\"}} {sample}\n"))
+		.collect();
+
+	let body = format!("# HELP {name} {help}\n# TYPE {name} gauge\n{samples}");
+	([(CONTENT_TYPE, CONTENT_TYPE_PROMETHEUS)], body).into_response()
+}
+
+/// # `GET /_conduwuit/supported_mscs`
+///
+/// conduwuit-specific API to list the identifiers of the MSCs this server
+/// implements, as a flat alternative to the unstable features of `/versions`.
+pub(crate) async fn conduwuit_supported_mscs() -> Result<impl IntoResponse> {
+	let mut mscs: Vec<&str> = UNSTABLE_FEATURES
+		.iter()
+		.filter_map(|feature| feature.msc)
+		.collect();
+
+	mscs.sort_unstable();
+	mscs.dedup();
+
+	Ok(Json(mscs))
+}
+
+/// # `GET /_conduwuit/mscs`
+///
+/// conduwuit-specific API describing each MSC this server implements: the
+/// unstable feature advertising it, a link to the proposal and whether
+/// `/versions` currently advertises it as enabled.
+pub(crate) async fn conduwuit_mscs() -> Result<impl IntoResponse> {
+	let enabled = supported_versions().unstable_features;
+	let mscs: Vec<_> = UNSTABLE_FEATURES
+		.iter()
+		.filter_map(|feature| {
+			let msc = feature.msc?;
+			Some(serde_json::json!({
+				"msc": msc,
+				"feature": feature.name,
+				"description": feature.description,
+				"url": feature.url,
+				"enabled": enabled.get(feature.name).copied().unwrap_or(false),
+			}))
+		})
+		.collect();
+
+	Ok(Json(mscs))
+}
+
+/// # `GET /_conduwuit/federation_health`
+///
+/// conduwuit-specific API for server administrators summarising federation
+/// with the most recently contacted remote servers: the outcome of the last
+/// transactions, the cached destination and whether resolution is refused by
+/// the circuit breaker.
+pub(crate) async fn conduwuit_federation_health(
+	State(services): State<crate::State>,
+	bearer: Option<TypedHeader<Authorization<Bearer>>>,
 ) -> Result<impl IntoResponse> {
-	let user_count = services.users.list_local_users().count().await;
+	const SAMPLE: usize = 100;
+
+	admin_from_bearer(&services, bearer).await?;
+
+	let timestamp = |time: Option<SystemTime>| {
+		time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+			.map(|since| since.as_secs())
+	};
+
+	let mut servers = Vec::new();
+	for (server_name, contact) in services.sending.contacts.recent(SAMPLE) {
+		let cached = services
+			.resolver
+			.cache
+			.get_destination(&server_name)
+			.await
+			.ok();
+		let circuit_open = services.resolver.breaker.is_open(server_name.as_str())
+			|| cached
+				.as_ref()
+				.is_some_and(|CachedDest { dest, .. }| match dest {
+					| FedDest::Named(host, _) => services.resolver.breaker.is_open(host),
+					| FedDest::Literal(_) => false,
+				});
+
+		servers.push(serde_json::json!({
+			"server_name": server_name,
+			"last_success_ts": timestamp(contact.last_success),
+			"last_failure_ts": timestamp(contact.last_failure),
+			"destination": cached.as_ref().map(|cached| cached.dest.to_string()),
+			"destination_expires_ts": timestamp(cached.map(|cached| cached.expire)),
+			"circuit_open": circuit_open,
+		}));
+	}
 
 	Ok(Json(serde_json::json!({
-		"count": user_count
+		"servers": servers,
 	})))
 }
+
+/// # `GET /_conduwuit/signing_keys`
+///
+/// conduwuit-specific API for server administrators listing this server's
+/// signing keys: the active key with the validity `/_matrix/key/v2/server`
+/// advertises for it, and any old keys with when they expired, to check key
+/// rotation. Admin-only, as it exposes the lifecycle of the keys.
+pub(crate) async fn conduwuit_signing_keys(
+	State(services): State<crate::State>,
+	bearer: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Result<impl IntoResponse> {
+	admin_from_bearer(&services, bearer).await?;
+
+	let server_name = services.globals.server_name();
+	let (active_key_id, active_key) = services.server_keys.active_verify_key();
+	let stored = services
+		.server_keys
+		.signing_keys_for(server_name)
+		.await
+		.ok();
+
+	let mut old_keys = BTreeMap::new();
+	if let Some(keys) = &stored {
+		for (key_id, key) in &keys.verify_keys {
+			if key_id.as_str() != active_key_id.as_str() {
+				old_keys.insert(key_id, (&key.key, None));
+			}
+		}
+
+		for (key_id, old) in &keys.old_verify_keys {
+			old_keys.insert(key_id, (&old.key, Some(old.expired_ts)));
+		}
+	}
+
+	let old_keys: Vec<_> = old_keys
+		.into_iter()
+		.map(|(key_id, (key, expired_ts))| {
+			serde_json::json!({
+				"key_id": key_id,
+				"key": key,
+				"expired_ts": expired_ts,
+			})
+		})
+		.collect();
+
+	Ok(Json(serde_json::json!({
+		"server_name": server_name,
+		"active": {
+			"key_id": active_key_id,
+			"key": active_key.key,
+			"valid_until_ts": crate::server::valid_until_ts(),
+		},
+		"old": old_keys,
+		"stored_valid_until_ts": stored.map(|keys| keys.valid_until_ts),
+	})))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ClientFeaturesQuery {
+	user_agent: Option<String>,
+	/// Whether the client would authenticate its request; defaults to true.
+	authenticated: Option<bool>,
+}
+
+/// # `GET /_conduwuit/client_features`
+///
+/// conduwuit-specific API for server administrators reporting the spec
+/// versions and unstable features `/versions` advertises to a client with the
+/// given `user_agent`, along with the `unstable_features_by_user_agent`
+/// pattern it matched, if any.
+pub(crate) async fn conduwuit_client_features(
+	State(services): State<crate::State>,
+	bearer: Option<TypedHeader<Authorization<Bearer>>>,
+	Query(query): Query<ClientFeaturesQuery>,
+) -> Result<impl IntoResponse> {
+	admin_from_bearer(&services, bearer).await?;
+
+	let user_agent = query.user_agent.as_deref();
+	let authenticated = query.authenticated.unwrap_or(true);
+	let profile = user_agent
+		.and_then(|ua| user_agent_profile(&services.server.config, ua))
+		.map(|(pattern, _)| pattern);
+
+	let versions = client_versions(&services.server.config, user_agent, authenticated);
+
+	Ok(Json(serde_json::json!({
+		"user_agent": user_agent,
+		"authenticated": authenticated,
+		"matched_pattern": profile,
+		"versions": versions.versions,
+		"unstable_features": versions.unstable_features,
+	})))
+}
+
+/// # `GET /_conduwuit/room_defaults`
+///
+/// conduwuit-specific API reporting the room creation defaults of the current
+/// configuration: the default room version, the versions available and
+/// whether each is accepted, and the policy applied when creating a room, so
+/// clients and bots can match it without trial and error.
+pub(crate) async fn conduwuit_room_defaults(
+	State(services): State<crate::State>,
+) -> Result<impl IntoResponse> {
+	let config = &services.server.config;
+	let available: BTreeMap<_, _> = Server::available_room_versions()
+		.map(|(version, stability)| {
+			let supported = services.server.supported_room_version(&version);
+			(version, serde_json::json!({ "stability": stability, "supported": supported }))
+		})
+		.collect();
+
+	Ok(Json(serde_json::json!({
+		"default_room_version": config.default_room_version,
+		"available_room_versions": available,
+		"allow_unstable_room_versions": config.allow_unstable_room_versions,
+		"default_presets": {
+			"public": RoomPreset::PublicChat,
+			"private": RoomPreset::PrivateChat,
+		},
+		"lockdown_public_room_directory": config.lockdown_public_room_directory,
+	})))
+}
+
+/// # `GET /_conduwuit/short_id_tables`
+///
+/// conduwuit-specific API for server administrators reporting the number of
+/// entries in each table mapping to short IDs, as last sampled every
+/// `short_id_table_sample_interval`, to forecast storage growth. Served in the
+/// Prometheus text format when the client accepts `text/plain`.
+pub(crate) async fn conduwuit_short_id_tables(
+	State(services): State<crate::State>,
+	bearer: Option<TypedHeader<Authorization<Bearer>>>,
+	headers: HeaderMap,
+) -> Result<Response> {
+	admin_from_bearer(&services, bearer).await?;
+
+	let sizes = match services.rooms.short.last_table_sizes() {
+		| Some(sizes) => sizes,
+		| None => services.rooms.short.sample_table_sizes().await,
+	};
+
+	if accepts_prometheus(&headers) {
+		return Ok(prometheus_labeled_gauge(
+			"conduwuit_short_id_table_entries",
+			"Estimated entries in each table mapping to short IDs.",
+			"table",
+			&sizes.tables,
+		));
+	}
+
+	let sampled_ts = sizes
+		.at
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs();
+
+	Ok(Json(serde_json::json!({
+		"sampled_ts": sampled_ts,
+		"tables": sizes.tables.into_iter().collect::<BTreeMap<_, _>>(),
+	}))
+	.into_response())
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ShortRoomIdsQuery {
+	/// The last room ID of the previous page.
+	since: Option<OwnedRoomId>,
+	limit: Option<usize>,
+}
+
+/// # `GET /_conduwuit/shortroomids`
+///
+/// conduwuit-specific API for server administrators listing every room the
+/// server has assigned a shortroomid, ordered by room ID. Paginated with
+/// `since` set to the `next_batch` of the previous page; the first page also
+/// includes the total number of rooms.
+pub(crate) async fn conduwuit_shortroomids(
+	State(services): State<crate::State>,
+	bearer: Option<TypedHeader<Authorization<Bearer>>>,
+	Query(query): Query<ShortRoomIdsQuery>,
+) -> Result<impl IntoResponse> {
+	const DEFAULT_LIMIT: usize = 100;
+	const MAX_LIMIT: usize = 1000;
+
+	admin_from_bearer(&services, bearer).await?;
+
+	let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+	let rooms: Vec<_> = services
+		.rooms
+		.short
+		.shortroomids_after(query.since.as_deref())
+		.take(limit)
+		.collect()
+		.await;
+
+	let next_batch = (rooms.len() == limit)
+		.then(|| rooms.last().map(|(room_id, _)| room_id.clone()))
+		.flatten();
+
+	let total = match query.since {
+		| None => Some(services.rooms.short.count_shortroomids().await),
+		| Some(_) => None,
+	};
+
+	let rooms: Vec<_> = rooms
+		.into_iter()
+		.map(|(room_id, shortroomid)| {
+			serde_json::json!({
+				"room_id": room_id,
+				"shortroomid": shortroomid,
+			})
+		})
+		.collect();
+
+	Ok(Json(serde_json::json!({
+		"rooms": rooms,
+		"next_batch": next_batch,
+		"total": total,
+	})))
+}
+
+/// Authenticates a conduwuit-specific admin endpoint by the access token,
+/// returning the server administrator it belongs to.
+async fn admin_from_bearer(
+	services: &Services,
+	bearer: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Result<OwnedUserId> {
+	let Some(TypedHeader(Authorization(bearer))) = bearer else {
+		return Err!(Request(MissingToken("Missing access token.")));
+	};
+
+	let Ok((user_id, _)) = services.users.find_from_token(bearer.token()).await else {
+		return Err(Error::BadRequest(
+			ErrorKind::UnknownToken { soft_logout: false },
+			"Unknown access token.",
+		));
+	};
+
+	if !services.users.is_admin(&user_id).await {
+		return Err!(Request(Forbidden("Only server administrators can use this endpoint.")));
+	}
+
+	Ok(user_id)
+}
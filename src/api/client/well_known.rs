@@ -1,32 +1,104 @@
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{
+	extract::State,
+	response::{IntoResponse, Response},
+	Json,
+};
+use base64::{engine::general_purpose, Engine as _};
+use conduwuit::utils::calculate_hash;
+use http::{
+	header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, HOST, IF_NONE_MATCH},
+	HeaderMap, StatusCode,
+};
 use ruma::api::client::{
-	discovery::{
-		discover_homeserver::{self, HomeserverInfo, SlidingSyncProxyInfo},
-		discover_support::{self, Contact},
-	},
+	discovery::discover_support::{self, Contact},
 	error::ErrorKind,
 };
+use service::Services;
 
 use crate::{Error, Result, Ruma};
 
 /// # `GET /.well-known/matrix/client`
 ///
-/// Returns the .well-known URL if it is configured, otherwise returns 404.
+/// Returns whichever of the homeserver, sliding sync proxy and identity server
+/// URLs are configured, or 404 if none of them are. The homeserver URL is
+/// selected by the request's Host header from `client_by_host` where listed.
 pub(crate) async fn well_known_client(
 	State(services): State<crate::State>,
-	_body: Ruma<discover_homeserver::Request>,
-) -> Result<discover_homeserver::Response> {
-	let client_url = match services.server.config.well_known.client.as_ref() {
-		| Some(url) => url.to_string(),
-		| None => return Err(Error::BadRequest(ErrorKind::NotFound, "Not found.")),
+	headers: HeaderMap,
+) -> Result<Response> {
+	let well_known = &services.server.config.well_known;
+	let client = request_host(&headers)
+		.and_then(|host| well_known.client_by_host.get(&host))
+		.or(well_known.client.as_ref());
+
+	let sliding_sync_proxy = well_known.sliding_sync_proxy.as_ref().or(client);
+
+	let mut response = serde_json::Map::new();
+	if let Some(url) = client {
+		response.insert("m.homeserver".into(), serde_json::json!({ "base_url": url }));
+	}
+
+	if let Some(url) = well_known.identity_server.as_ref() {
+		response.insert("m.identity_server".into(), serde_json::json!({ "base_url": url }));
+	}
+
+	if let Some(url) = sliding_sync_proxy {
+		response.insert("org.matrix.msc3575.proxy".into(), serde_json::json!({ "url": url }));
+	}
+
+	if response.is_empty() {
+		return Err(Error::BadRequest(ErrorKind::NotFound, "Not found."));
+	}
+
+	Ok(cacheable_json(&services, &headers, &response.into()))
+}
+
+/// The host a request was made to, lowercased and without any port, as the
+/// key of `well_known.client_by_host`.
+fn request_host(headers: &HeaderMap) -> Option<String> {
+	let host = headers.get(HOST)?.to_str().ok()?;
+	let host = match host.rsplit_once(':') {
+		| Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+		| _ => host,
 	};
 
-	Ok(discover_homeserver::Response {
-		homeserver: HomeserverInfo { base_url: client_url.clone() },
-		identity_server: None,
-		sliding_sync_proxy: Some(SlidingSyncProxyInfo { url: client_url }),
-		tile_server: None,
-	})
+	Some(host.to_ascii_lowercase())
+}
+
+/// Serves a well-known response with an ETag of its body, cacheable for
+/// `well_known_cache_max_age` seconds. A request whose `If-None-Match` matches
+/// the ETag is answered with 304 Not Modified and no body.
+pub(crate) fn cacheable_json(
+	services: &Services,
+	headers: &HeaderMap,
+	body: &serde_json::Value,
+) -> Response {
+	let body = body.to_string();
+	let hash = calculate_hash([body.as_bytes()].into_iter());
+	let etag = format!("\"{}\"", general_purpose::URL_SAFE_NO_PAD.encode(hash));
+	let max_age = services.server.config.well_known_cache_max_age;
+	let cache_control = format!("public, max-age={max_age}");
+
+	let not_modified = headers
+		.get_all(IF_NONE_MATCH)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','))
+		.map(str::trim)
+		.any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+
+	if not_modified {
+		return (StatusCode::NOT_MODIFIED, [(ETAG, etag), (CACHE_CONTROL, cache_control)])
+			.into_response();
+	}
+
+	let headers = [
+		(CONTENT_TYPE, "application/json".to_owned()),
+		(ETAG, etag),
+		(CACHE_CONTROL, cache_control),
+	];
+
+	(headers, body).into_response()
 }
 
 /// # `GET /.well-known/matrix/support`
@@ -9,10 +9,10 @@
 
 use axum::{
 	response::{IntoResponse, Redirect},
-	routing::{any, get, post},
+	routing::{any, get, post, MethodRouter},
 	Router,
 };
-use conduwuit::{err, Server};
+use conduwuit::{config::Config, err, Server};
 use http::{uri, Uri};
 
 use self::handler::RouterExt;
@@ -184,8 +184,40 @@ pub fn build(router: Router<State>, server: &Server) -> Router<State> {
 			get(client::get_room_summary_legacy)
 		)
 		.ruma_route(&client::well_known_support)
-		.ruma_route(&client::well_known_client)
-		.route("/_conduwuit/server_version", get(client::conduwuit_server_version))
+		.route("/.well-known/matrix/client", get(client::well_known_client))
+		.route(
+			"/_conduwuit/server_version",
+			conduwuit_route(get(client::conduwuit_server_version), config),
+		)
+		.route(
+			"/_conduwuit/server_info",
+			conduwuit_route(get(client::conduwuit_server_info), config),
+		)
+		.route(
+			"/_conduwuit/supported_mscs",
+			conduwuit_route(get(client::conduwuit_supported_mscs), config),
+		)
+		.route("/_conduwuit/mscs", conduwuit_route(get(client::conduwuit_mscs), config))
+		.route(
+			"/_conduwuit/client_features",
+			conduwuit_route(get(client::conduwuit_client_features), config),
+		)
+		.route(
+			"/_conduwuit/room_defaults",
+			conduwuit_route(get(client::conduwuit_room_defaults), config),
+		)
+		.route(
+			"/_conduwuit/signing_keys",
+			conduwuit_route(get(client::conduwuit_signing_keys), config),
+		)
+		.route(
+			"/_conduwuit/short_id_tables",
+			conduwuit_route(get(client::conduwuit_short_id_tables), config),
+		)
+		.route(
+			"/_conduwuit/shortroomids",
+			conduwuit_route(get(client::conduwuit_shortroomids), config),
+		)
 		.ruma_route(&client::room_initial_sync_route)
 		.route("/client/server.json", get(client::syncv3_client_server_json));
 
@@ -222,16 +254,24 @@ pub fn build(router: Router<State>, server: &Server) -> Router<State> {
 			.ruma_route(&server::claim_keys_route)
 			.ruma_route(&server::get_openid_userinfo_route)
 			.ruma_route(&server::get_hierarchy_route)
-			.ruma_route(&server::well_known_server)
+			.route("/.well-known/matrix/server", get(server::well_known_server))
 			.ruma_route(&server::get_content_route)
 			.ruma_route(&server::get_content_thumbnail_route)
-			.route("/_conduwuit/local_user_count", get(client::conduwuit_local_user_count));
+			.route(
+				"/_conduwuit/local_user_count",
+				conduwuit_route(get(client::conduwuit_local_user_count), config),
+			)
+			.route(
+				"/_conduwuit/federation_health",
+				conduwuit_route(get(client::conduwuit_federation_health), config),
+			);
 	} else {
 		router = router
 			.route("/_matrix/federation/*path", any(federation_disabled))
 			.route("/.well-known/matrix/server", any(federation_disabled))
 			.route("/_matrix/key/*path", any(federation_disabled))
-			.route("/_conduwuit/local_user_count", any(federation_disabled));
+			.route("/_conduwuit/local_user_count", any(federation_disabled))
+			.route("/_conduwuit/federation_health", any(federation_disabled));
 	}
 
 	if config.allow_legacy_media {
@@ -275,6 +315,31 @@ pub fn build(router: Router<State>, server: &Server) -> Router<State> {
 	router
 }
 
+/// Applies layers common to the conduwuit-specific `/_conduwuit/*` endpoints.
+#[cfg_attr(not(feature = "gzip_compression"), allow(unused_variables))]
+fn conduwuit_route(route: MethodRouter<State>, config: &Config) -> MethodRouter<State> {
+	#[cfg(feature = "gzip_compression")]
+	if config.gzip_conduwuit_endpoints && !config.gzip_compression {
+		use tower_http::compression::{
+			predicate::{DefaultPredicate, Predicate, SizeAbove},
+			CompressionLayer,
+		};
+
+		// Small responses such as the server version are left uncompressed.
+		let predicate = DefaultPredicate::new().and(SizeAbove::new(1024));
+		let layer = CompressionLayer::new()
+			.gzip(true)
+			.no_br()
+			.no_deflate()
+			.no_zstd()
+			.compress_when(predicate);
+
+		return route.layer(layer);
+	}
+
+	route
+}
+
 async fn redirect_legacy_preview(uri: Uri) -> impl IntoResponse {
 	let path = "/_matrix/client/v1/media/preview_url";
 	let query = uri.query().unwrap_or_default();
@@ -57,7 +57,7 @@ pub(crate) async fn get_server_keys_route(
 	Ok(Json(response))
 }
 
-fn valid_until_ts() -> MilliSecondsSinceUnixEpoch {
+pub(crate) fn valid_until_ts() -> MilliSecondsSinceUnixEpoch {
 	let dur = Duration::from_secs(86400 * 7);
 	let timepoint = timepoint_from_now(dur).expect("SystemTime should not overflow");
 	MilliSecondsSinceUnixEpoch::from_system_time(timepoint).expect("UInt should not overflow")
@@ -1,19 +1,21 @@
-use axum::extract::State;
-use ruma::api::{client::error::ErrorKind, federation::discovery::discover_homeserver};
+use axum::{extract::State, response::Response};
+use http::HeaderMap;
+use ruma::api::client::error::ErrorKind;
 
-use crate::{Error, Result, Ruma};
+use crate::{client::cacheable_json, Error, Result};
 
 /// # `GET /.well-known/matrix/server`
 ///
 /// Returns the .well-known URL if it is configured, otherwise returns 404.
 pub(crate) async fn well_known_server(
 	State(services): State<crate::State>,
-	_body: Ruma<discover_homeserver::Request>,
-) -> Result<discover_homeserver::Response> {
-	Ok(discover_homeserver::Response {
-		server: match services.server.config.well_known.server.as_ref() {
-			| Some(server_name) => server_name.to_owned(),
-			| None => return Err(Error::BadRequest(ErrorKind::NotFound, "Not found.")),
-		},
-	})
+	headers: HeaderMap,
+) -> Result<Response> {
+	let Some(server_name) = services.server.config.well_known.server.as_ref() else {
+		return Err(Error::BadRequest(ErrorKind::NotFound, "Not found."));
+	};
+
+	let response = serde_json::json!({ "m.server": server_name });
+
+	Ok(cacheable_json(&services, &headers, &response))
 }
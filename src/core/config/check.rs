@@ -102,6 +102,124 @@ pub fn check(config: &Config) -> Result {
 		});
 	}
 
+	if let Some(upstream) = &config.dns_dot_upstream {
+		if upstream.is_empty() || upstream.contains([':', '/']) {
+			return Err!(Config(
+				"dns_dot_upstream",
+				"Upstream {upstream:?} must be a hostname without a port."
+			));
+		}
+
+		if config.dns_dot_bootstrap_ip.is_none() {
+			return Err!(Config(
+				"dns_dot_bootstrap_ip",
+				"Must be set for the dns_dot_upstream to be reached."
+			));
+		}
+	} else if config.dns_dot_bootstrap_ip.is_some() {
+		return Err!(Config("dns_dot_upstream", "Must be set when dns_dot_bootstrap_ip is."));
+	}
+
+	if !config.dns_use_system_conf
+		&& config.dns_nameservers.is_empty()
+		&& config.dns_dot_upstream.is_none()
+	{
+		return Err!(Config(
+			"dns_nameservers",
+			"At least one nameserver must be specified when dns_use_system_conf is disabled."
+		));
+	}
+
+	for (pattern, strategy) in &config.ip_lookup_strategy_rules {
+		if !(1..=5).contains(strategy) {
+			return Err!(Config(
+				"ip_lookup_strategy_rules",
+				"Strategy {strategy} for {pattern:?} must be between 1 and 5."
+			));
+		}
+
+		let domain = pattern.strip_prefix("*.").unwrap_or(pattern);
+		if domain.is_empty() || domain.contains('*') {
+			return Err!(Config(
+				"ip_lookup_strategy_rules",
+				"Pattern {pattern:?} must be a domain, optionally prefixed by \"*.\"."
+			));
+		}
+	}
+
+	if !["v4", "v6", "none"].contains(&config.dns_prefer_family.as_str()) {
+		return Err!(Config("dns_prefer_family", "Must be one of \"v4\", \"v6\" or \"none\"."));
+	}
+
+	for query in config.dns_tcp_fallback_by_query.keys() {
+		if !["ip", "srv", "txt"].contains(&query.as_str()) {
+			return Err!(Config(
+				"dns_tcp_fallback_by_query",
+				"Unknown query type {query:?}; must be \"ip\", \"srv\" or \"txt\"."
+			));
+		}
+	}
+
+	let max_addresses = config.dns_max_addresses_per_family.saturating_mul(2);
+	let min_addresses = std::iter::once(config.dns_min_addresses)
+		.chain(config.dns_min_addresses_overrides.values().copied());
+	for min in min_addresses {
+		if min == 0 {
+			return Err!(Config("dns_min_addresses", "Must be at least 1."));
+		}
+
+		if max_addresses > 0 && min > max_addresses {
+			return Err!(Config(
+				"dns_min_addresses",
+				"{min} addresses can never be usable with dns_max_addresses_per_family of {}.",
+				config.dns_max_addresses_per_family
+			));
+		}
+	}
+
+	for name in config.dns_min_addresses_overrides.keys() {
+		if name.is_empty() || name.ends_with('.') || *name != name.to_ascii_lowercase() {
+			return Err!(Config(
+				"dns_min_addresses_overrides",
+				"Name {name:?} must be a lowercase hostname without a trailing dot."
+			));
+		}
+	}
+
+	if config.federation_default_port == 0 {
+		return Err!(Config("federation_default_port", "Must be a valid port."));
+	}
+
+	if config.dns_quarantine_threshold == 0 {
+		return Err!(Config("dns_quarantine_threshold", "Must be at least 1."));
+	}
+
+	for (zone, nameservers) in &config.dns_forward_zones {
+		if zone.is_empty() || zone.starts_with('.') || zone.contains('*') {
+			return Err!(Config("dns_forward_zones", "Zone {zone:?} must be a domain."));
+		}
+
+		if nameservers.is_empty() {
+			return Err!(Config("dns_forward_zones", "Zone {zone:?} has no nameservers."));
+		}
+	}
+
+	for (host, url) in &config.well_known.client_by_host {
+		if host.is_empty() || host.contains([':', '/']) || *host != host.to_ascii_lowercase() {
+			return Err!(Config(
+				"well_known.client_by_host",
+				"Host {host:?} must be a lowercase hostname without a port."
+			));
+		}
+
+		if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+			return Err!(Config(
+				"well_known.client_by_host",
+				"Client URL {url} of {host:?} must be an HTTP(S) URL."
+			));
+		}
+	}
+
 	// rocksdb does not allow max_log_files to be 0
 	if config.rocksdb_max_log_files == 0 {
 		return Err!(Config(
@@ -268,6 +268,56 @@ pub struct Config {
 	#[serde(default = "default_roomid_spacehierarchy_cache_capacity")]
 	pub roomid_spacehierarchy_cache_capacity: u32,
 
+	/// Retain recently resolved mappings between event IDs and their short IDs
+	/// in memory, and serve them when reading the database fails. These
+	/// mappings never change once created, so a retained entry is never stale.
+	/// Capacity follows "shorteventid_cache_capacity" and
+	/// "eventidshort_cache_capacity".
+	#[serde(default)]
+	pub short_id_cache_fallback: bool,
+
+	/// When a stored shorteventid turns out to be corrupt while resolving a
+	/// batch of events, log it and allocate a new one in its place rather than
+	/// failing with a database error. This aids recovering a partially corrupt
+	/// database; the default fails fast so corruption is noticed.
+	#[serde(default)]
+	pub recreate_corrupt_short_ids: bool,
+
+	/// Seconds between background scans sampling the short ID tables for
+	/// entries whose reverse mapping lacks a matching forward mapping, so that
+	/// corruption is noticed before it is read. Mismatches are logged as
+	/// errors; the last result is shown by `!admin rooms short
+	/// last-integrity-scan`. 0 disables the periodic scan.
+	#[serde(default)]
+	pub short_id_scan_interval: u64,
+
+	/// Entries sampled from each short ID table per integrity scan, bounding
+	/// the cost of a scan.
+	///
+	/// default: 1000
+	#[serde(default = "default_short_id_scan_sample")]
+	pub short_id_scan_sample: usize,
+
+	/// Seconds between samples of the number of entries in each short ID
+	/// table, served by `/_conduwuit/short_id_tables` to graph their growth.
+	/// RocksDB's estimate is used rather than an exact count. 0 disables the
+	/// periodic sample; the route then samples on request.
+	#[serde(default)]
+	pub short_id_table_sample_interval: u64,
+
+	/// Replay/test mode: seed the global counter of a newly created database
+	/// with this value, so that short IDs and other counts are allocated
+	/// deterministically given the same order of insertions. Has no effect
+	/// on an existing database.
+	///
+	/// NEVER use this in production alongside live traffic; concurrent
+	/// requests interleave allocations and defeat the determinism anyway.
+	/// Intended only for tests and replaying recorded inputs into a fresh
+	/// database.
+	///
+	/// example: 1000000
+	pub short_id_seed_count: Option<u64>,
+
 	/// Maximum entries stored in DNS memory-cache. The size of an entry may
 	/// vary so please take care if raising this value excessively. Only
 	/// decrease this when using an external DNS cache. Please note that
@@ -288,6 +338,44 @@ pub struct Config {
 	#[serde(default = "default_dns_min_ttl")]
 	pub dns_min_ttl: u64,
 
+	/// Hard floor in seconds for how long conduwuit retains resolved addresses
+	/// in its own resolver layer, regardless of the record's TTL or
+	/// `dns_min_ttl`. This reduces query volume to slow resolvers for
+	/// frequently-contacted servers, at the cost of potentially serving
+	/// slightly stale addresses after a remote changes their DNS.
+	///
+	/// Set to 0 to disable.
+	///
+	/// default: 0
+	#[serde(default)]
+	pub dns_ttl_floor: u64,
+
+	/// Number of consecutive resolution failures of a name within
+	/// `dns_circuit_breaker_window` after which conduwuit stops attempting to
+	/// resolve it for `dns_circuit_breaker_cooldown` seconds. This avoids
+	/// hammering slow or broken resolvers for dead destinations.
+	///
+	/// Set to 0 to disable.
+	///
+	/// default: 0
+	#[serde(default)]
+	pub dns_circuit_breaker_threshold: u32,
+
+	/// Window in seconds in which consecutive resolution failures are counted
+	/// towards `dns_circuit_breaker_threshold`.
+	///
+	/// default: 60
+	#[serde(default = "default_dns_circuit_breaker_window")]
+	pub dns_circuit_breaker_window: u64,
+
+	/// Time in seconds resolution of a name is refused once its circuit
+	/// breaker has opened. After this elapses a single attempt is permitted;
+	/// another failure re-opens the circuit.
+	///
+	/// default: 300
+	#[serde(default = "default_dns_circuit_breaker_cooldown")]
+	pub dns_circuit_breaker_cooldown: u64,
+
 	/// Minimum time-to-live in seconds for NXDOMAIN entries in the DNS cache.
 	/// This value is critical for the server to federate efficiently.
 	/// NXDOMAIN's are assumed to not be returning to the federation and
@@ -314,11 +402,45 @@ pub struct Config {
 	#[serde(default = "default_dns_timeout")]
 	pub dns_timeout: u64,
 
+	/// Per-name overrides of `dns_timeout`, in seconds, for peers known to
+	/// resolve slowly. Resolution of a listed name is retried after timing out
+	/// until its overall budget elapses. Names are matched as resolved, i.e.
+	/// the hostname of the destination rather than necessarily the server
+	/// name.
+	///
+	/// example: { "slow.example.com" = 30 }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub dns_timeout_overrides: BTreeMap<String, u64>,
+
+	/// Address lookups taking longer than this many milliseconds are logged
+	/// as a warning naming the name and the time taken, at most once per
+	/// name a minute, to point out which destinations resolve slowly. 0
+	/// disables the warning.
+	///
+	/// default: 2000
+	#[serde(default = "default_dns_slow_lookup_threshold_ms")]
+	pub dns_slow_lookup_threshold_ms: u64,
+
 	/// Fallback to TCP on DNS errors. Set this to false if unsupported by
 	/// nameserver.
 	#[serde(default = "true_fn")]
 	pub dns_tcp_fallback: bool,
 
+	/// Per query type overrides of `dns_tcp_fallback`, keyed by "ip" (A and
+	/// AAAA), "srv" or "txt". Queries whose answers can be large, such as SRV
+	/// for servers with many targets, can fall back to TCP while others stay
+	/// on UDP. Truncated UDP answers are retried over TCP regardless. Query
+	/// types not listed follow `dns_tcp_fallback`. Does not apply to
+	/// `dns_forward_zones`.
+	///
+	/// example: { "srv" = true, "ip" = false }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub dns_tcp_fallback_by_query: BTreeMap<String, bool>,
+
 	/// Enable to query all nameservers until the domain is found. Referred to
 	/// as "trust_negative_responses" in hickory_resolver. This can avoid
 	/// useless DNS queries if the first nameserver responds with NXDOMAIN or
@@ -335,6 +457,220 @@ pub struct Config {
 	#[serde(default)]
 	pub query_over_tcp_only: bool,
 
+	/// Build the DNS resolver from the system configuration (e.g.
+	/// /etc/resolv.conf). When disabled, the system configuration is ignored
+	/// entirely and only `dns_nameservers` are queried, making resolution
+	/// independent of the host or container image.
+	#[serde(default = "true_fn")]
+	pub dns_use_system_conf: bool,
+
+	/// Nameservers queried when `dns_use_system_conf` is disabled. Each entry
+	/// is an IP address and port. Required when `dns_use_system_conf` is
+	/// disabled; ignored otherwise.
+	///
+	/// example: ["1.1.1.1:53", "[2606:4700:4700::1111]:53"]
+	///
+	/// default: []
+	#[serde(default)]
+	pub dns_nameservers: Vec<SocketAddr>,
+
+	/// Hostname of a DNS-over-TLS upstream to send every query to, encrypted
+	/// and integrity-protected, in place of the nameservers of the system
+	/// configuration or `dns_nameservers`. The upstream's certificate is
+	/// verified against this name using the system's trusted roots;
+	/// verification cannot be disabled. Requires `dns_dot_bootstrap_ip`.
+	/// Unset resolves as configured by `dns_use_system_conf`.
+	///
+	/// example: "dns.quad9.net"
+	pub dns_dot_upstream: Option<String>,
+
+	/// Address at which the `dns_dot_upstream` is reached, as it cannot be
+	/// resolved through itself. Required when `dns_dot_upstream` is set.
+	///
+	/// example: "9.9.9.9"
+	pub dns_dot_bootstrap_ip: Option<IpAddr>,
+
+	/// Port of the `dns_dot_upstream`.
+	///
+	/// default: 853
+	#[serde(default = "default_dns_dot_port")]
+	pub dns_dot_port: u16,
+
+	/// Nameservers consulted only when resolving through the primary
+	/// nameservers fails or times out, rather than being part of the normal
+	/// rotation. Useful when the primary nameservers are a strict internal
+	/// resolver and these are public ones for external names. Each entry is
+	/// an IP address and port.
+	///
+	/// example: ["1.1.1.1:53", "[2606:4700:4700::1111]:53"]
+	///
+	/// default: []
+	#[serde(default)]
+	pub dns_fallback_nameservers: Vec<SocketAddr>,
+
+	/// Conditional forwarding for split-horizon DNS: names within each zone,
+	/// i.e. the zone itself and all of its subdomains, are resolved through the
+	/// nameservers listed for it rather than the default ones. Where zones are
+	/// nested the most specific applies. Forwarded names ignore
+	/// `ip_lookup_strategy_rules`, using `ip_lookup_strategy`.
+	///
+	/// example: { "corp.internal" = ["10.0.0.53:53"] }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub dns_forward_zones: BTreeMap<String, Vec<SocketAddr>>,
+
+	/// Serve a remote server's cached destination (its .well-known delegation
+	/// and SRV results) for up to this many seconds past its expiry, while it
+	/// is resolved again in the background. Keeps federation with a server
+	/// fast while its .well-known is briefly unavailable. Entries stale for
+	/// longer are resolved before use. 0 disables serving stale entries.
+	#[serde(default)]
+	pub dns_destination_max_stale: u64,
+
+	/// Resolve our own server name, `dns_selftest_name` and each of the
+	/// `trusted_servers` as federation destinations at startup, logging any
+	/// failures as errors. This runs in the background and does not delay
+	/// startup, but with many trusted servers it issues many queries at once.
+	/// The self-test can also be run with `!admin query resolver self-test`.
+	#[serde(default)]
+	pub dns_selftest_on_startup: bool,
+
+	/// Resolve up to this many of the remote servers sharing the most rooms
+	/// with us as federation destinations at startup, so their destinations
+	/// and addresses are cached before federation traffic resumes. This runs
+	/// in the background and does not delay startup. Servers can also be
+	/// warmed with `!admin query resolver warm`. 0 disables warming.
+	#[serde(default)]
+	pub dns_warm_on_startup: usize,
+
+	/// Name known to resolve, used by the DNS self-test to tell a broken
+	/// resolver apart from a problem with a particular destination.
+	///
+	/// default: "matrix.org"
+	#[serde(default = "default_dns_selftest_name")]
+	pub dns_selftest_name: String,
+
+	/// Seconds between health probes of each upstream nameserver, resolving
+	/// `dns_selftest_name` through each in isolation. A nameserver failing
+	/// `dns_quarantine_threshold` consecutive probes is removed from the
+	/// rotation until a probe succeeds again, so a dead upstream stops adding
+	/// latency. Rebuilding the rotation empties the resolver's cache. Never
+	/// quarantines every nameserver. 0 disables probing and quarantine.
+	#[serde(default)]
+	pub dns_quarantine_interval: u64,
+
+	/// Consecutive failed health probes after which a nameserver is
+	/// quarantined; see `dns_quarantine_interval`.
+	///
+	/// default: 3
+	#[serde(default = "default_dns_quarantine_threshold")]
+	pub dns_quarantine_threshold: u32,
+
+	/// Number of dots a name must contain before it is first tried as an
+	/// absolute name rather than being expanded with the search domains.
+	/// Unset uses the system value (resolv.conf `options ndots`), or the
+	/// resolver's default of 1 when `dns_use_system_conf` is disabled.
+	///
+	/// example: 1
+	pub dns_ndots: Option<usize>,
+
+	/// Spread queries across the upstream nameservers in turn rather than
+	/// always trying them in order, like resolv.conf `options rotate`. This
+	/// overrides the system value.
+	#[serde(default = "true_fn")]
+	pub dns_rotate: bool,
+
+	/// Shuffle the order of the upstream nameservers before each query, so
+	/// that load is spread and a single slow nameserver is not always
+	/// tried first.
+	#[serde(default = "true_fn")]
+	pub dns_shuffle_servers: bool,
+
+	/// Set the AD (authentic data) bit on DNS queries, asking the nameservers
+	/// to report whether answers were validated with DNSSEC. conduwuit does no
+	/// DNSSEC validation of its own; this trusts the resolver's validation
+	/// entirely. Only enable this when every configured nameserver validates
+	/// DNSSEC and is reached over a trusted path (e.g. localhost), as the AD
+	/// bit is otherwise trivially forged.
+	#[serde(default)]
+	pub dns_trust_ad_bit: bool,
+
+	/// Set the RD (recursion desired) bit on DNS queries. Disable only when
+	/// the configured nameservers are non-recursive forwarders which must not
+	/// be asked to recurse.
+	#[serde(default = "true_fn")]
+	pub dns_recursion_desired: bool,
+
+	/// Order resolved addresses for Happy Eyeballs (RFC 8305): IPv6 and IPv4
+	/// addresses are interleaved, starting with IPv6. Connections are raced
+	/// against the other address family after a fixed delay chosen by the
+	/// HTTP client, which is not configurable; this ordering ensures an
+	/// address of each family is attempted early so a peer with one broken
+	/// family is still reached quickly. This only has an effect when
+	/// `ip_lookup_strategy` is 3, the only strategy returning both families.
+	#[serde(default)]
+	pub dns_happy_eyeballs: bool,
+
+	/// Never connect over IPv6: IPv6 addresses are discarded from every
+	/// resolution, override and IP literal, so no IPv6 socket is ever opened
+	/// for federation. Unlike an IPv4-only `ip_lookup_strategy`, this also
+	/// covers addresses which did not come from DNS. Remote servers reachable
+	/// only over IPv6 become unreachable.
+	#[serde(default)]
+	pub dns_disable_ipv6: bool,
+
+	/// Which address family to try first when a name resolves to both, as
+	/// the HTTP client attempts addresses in order: "v4", "v6" or "none" to
+	/// keep the resolver's order. Addresses are stably sorted by family, so the
+	/// order within each family is kept. This is independent of which families
+	/// are queried, see `ip_lookup_strategy`, and is ignored when
+	/// `dns_happy_eyeballs` is enabled, which always starts with IPv6.
+	///
+	/// default: "none"
+	#[serde(default = "default_dns_prefer_family")]
+	pub dns_prefer_family: String,
+
+	/// Maximum number of addresses of each family, IPv4 and IPv6, kept from a
+	/// resolved name, in the order received. Capping each family separately
+	/// keeps an answer flooded with addresses of one family from crowding out
+	/// the other, so dual-stack peers stay reachable. 0 keeps every address.
+	///
+	/// default: 32
+	#[serde(default = "default_dns_max_addresses_per_family")]
+	pub dns_max_addresses_per_family: usize,
+
+	/// Minimum number of distinct usable addresses, across both families,
+	/// a resolved name must yield; resolution yielding fewer fails as if the
+	/// name did not resolve. Raising this treats a peer whose DNS has degraded
+	/// to a single address as unreachable rather than relying on that address.
+	/// Counted after `dns_disable_ipv6` and `dns_max_addresses_per_family`
+	/// are applied.
+	///
+	/// default: 1
+	#[serde(default = "default_dns_min_addresses")]
+	pub dns_min_addresses: usize,
+
+	/// Per-name overrides of `dns_min_addresses`, for critical peers expected
+	/// to always resolve to several addresses. Names are matched as resolved,
+	/// i.e. the hostname of the destination rather than necessarily the
+	/// server name, regardless of case. They must be written in lowercase
+	/// without a trailing dot.
+	///
+	/// example: { "matrix.example.com" = 2 }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub dns_min_addresses_overrides: BTreeMap<String, usize>,
+
+	/// When a host resolves to several addresses, try the one the federation
+	/// sender last connected to successfully first, rather than repeating
+	/// failed attempts against a dead address ahead of it. Hints are kept in
+	/// memory per host and replaced whenever another address succeeds. This
+	/// only affects the federation client.
+	#[serde(default)]
+	pub dns_prefer_last_good_address: bool,
+
 	/// DNS A/AAAA record lookup strategy
 	///
 	/// Takes a number of one of the following options:
@@ -360,6 +696,18 @@ pub struct Config {
 	#[serde(default = "default_ip_lookup_strategy")]
 	pub ip_lookup_strategy: u8,
 
+	/// Per-domain overrides of `ip_lookup_strategy`, e.g. for networks only
+	/// reachable over one address family. Each key is either a domain matched
+	/// exactly, or `*.` followed by a domain to match all of its subdomains;
+	/// the most specific matching pattern applies. Values take the same
+	/// numbers as `ip_lookup_strategy`.
+	///
+	/// example: { "*.example.org" = 2 }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub ip_lookup_strategy_rules: BTreeMap<String, u8>,
+
 	/// Max request size for file uploads in bytes. Defaults to 20MB.
 	///
 	/// default: 20971520
@@ -417,10 +765,33 @@ pub struct Config {
 
 	/// Federation HTTP well-known resolution request timeout (seconds).
 	///
+	/// This and `well_known_conn_timeout` apply only to fetching
+	/// /.well-known/matrix/server during delegation, independently of the DNS
+	/// queries around it, which are bounded by `dns_timeout` and
+	/// `dns_attempts`.
+	///
 	/// default: 10
 	#[serde(default = "default_well_known_timeout")]
 	pub well_known_timeout: u64,
 
+	/// Maximum size in bytes of a remote server's /.well-known/matrix/server
+	/// response. Larger responses are discarded without being read in full,
+	/// as if the server had no .well-known, so a malicious server cannot
+	/// exhaust memory while being resolved.
+	///
+	/// default: 12288
+	#[serde(default = "default_well_known_max_size")]
+	pub well_known_max_size: usize,
+
+	/// How long clients and caching proxies may cache the well-known
+	/// responses served by conduwuit (seconds), advertised through
+	/// `Cache-Control`. Responses also carry an ETag so they can be
+	/// revalidated cheaply.
+	///
+	/// default: 3600
+	#[serde(default = "default_well_known_cache_max_age")]
+	pub well_known_cache_max_age: u64,
+
 	/// Federation client request timeout (seconds). You most definitely want
 	/// this to be high to account for extremely large room joins, slow
 	/// homeservers, your own resources etc.
@@ -561,6 +932,16 @@ pub struct Config {
 	#[serde(default)]
 	pub federation_loopback: bool,
 
+	/// Port connected to on a server's own name when it has neither a
+	/// .well-known delegation nor either of the SRV records, the final step of
+	/// server name resolution. The specification mandates 8448; only change
+	/// this for test federations running on non-standard ports. Ports given in
+	/// a server name or its delegation are unaffected.
+	///
+	/// default: 8448
+	#[serde(default = "default_federation_default_port")]
+	pub federation_default_port: u16,
+
 	/// Set this to true to require authentication on the normally
 	/// unauthenticated profile retrieval endpoints (GET)
 	/// "/_matrix/client/v3/profile/{userId}".
@@ -1273,6 +1654,14 @@ pub struct Config {
 	#[serde(default)]
 	pub gzip_compression: bool,
 
+	/// Set this to true to compress large responses from the
+	/// conduwuit-specific `/_conduwuit/*` endpoints using gzip when the client
+	/// accepts it, even if `gzip_compression` is disabled globally. This
+	/// option does nothing if conduwuit was not built with the
+	/// `gzip_compression` feature.
+	#[serde(default)]
+	pub gzip_conduwuit_endpoints: bool,
+
 	/// Set this to true for conduwuit to compress HTTP response bodies using
 	/// brotli. This option does nothing if conduwuit was not built with
 	/// `brotli_compression` feature. Please be aware that enabling HTTP
@@ -1312,6 +1701,29 @@ pub struct Config {
 	#[serde(default = "true_fn")]
 	pub allow_legacy_media: bool,
 
+	/// Compatibility shim tailoring the unstable features advertised by
+	/// /_matrix/client/versions to clients known to misbehave when offered
+	/// features they do not handle well. Keys are matched as substrings of the
+	/// client's User-Agent; a matching client is advertised only the unstable
+	/// features listed for it. Where several keys match, the first in lexical
+	/// order applies. Other clients are always advertised every feature.
+	///
+	/// This is a workaround for specific client bugs and not a way to disable
+	/// features; clients may use a feature regardless of it being advertised.
+	///
+	/// example: { "SomeBot/" = ["org.matrix.msc3575"] }
+	#[serde(default)]
+	pub unstable_features_by_user_agent: BTreeMap<String, Vec<String>>,
+
+	/// Advertise unstable features through /_matrix/client/versions only to
+	/// authenticated clients; unauthenticated requests are given just the
+	/// stable spec versions. This reduces what scanners can learn about the
+	/// server, but clients which check for unstable features before logging
+	/// in, e.g. for authenticated media or sliding sync, may behave as if the
+	/// features are unsupported until they log in.
+	#[serde(default)]
+	pub unstable_features_require_auth: bool,
+
 	#[serde(default = "true_fn")]
 	pub freeze_legacy_media: bool,
 
@@ -1402,6 +1814,20 @@ pub struct Config {
 	#[serde(default = "default_ip_range_denylist")]
 	pub ip_range_denylist: Vec<String>,
 
+	/// Vector list of hostnames that the resolver refuses to resolve, so that
+	/// conduwuit never sends requests to them; neither federation nor
+	/// fetching media or URL previews. Names are matched exactly and
+	/// case-insensitively; subdomains are not included.
+	///
+	/// Like `ip_range_denylist` this is not a guarantee, as a hostname may
+	/// have any number of aliases. Entries can be added and removed at runtime
+	/// with the `!admin query resolver deny-hostname` and `allow-hostname`
+	/// commands; such changes are lost on restart.
+	///
+	/// default: []
+	#[serde(default)]
+	pub hostname_denylist: Vec<String>,
+
 	/// Optional IP address or network interface-name to bind as the source of
 	/// URL preview requests. If not set, it will not bind to a specific
 	/// address or interface.
@@ -1826,6 +2252,17 @@ pub struct WellKnownConfig {
 	/// example: "https://matrix.example.com"
 	pub client: Option<Url>,
 
+	/// Client URLs served instead of `client` to requests for each virtual
+	/// host, selected by the request's Host header without its port. Hosts
+	/// not listed are served `client`. This lets one server delegate
+	/// clients differently per domain.
+	///
+	/// example: { "chat.example.org" = "https://matrix.example.org" }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub client_by_host: BTreeMap<String, Url>,
+
 	/// The server base domain of the URL with a specific port that the server
 	/// well-known file will serve. This should contain a port at the end, and
 	/// should not be a URL.
@@ -1833,6 +2270,18 @@ pub struct WellKnownConfig {
 	/// example: "matrix.example.com:443"
 	pub server: Option<OwnedServerName>,
 
+	/// The sliding sync proxy URL that the client well-known file will serve.
+	/// Defaults to the `client` URL when unset, as conduwuit serves sliding
+	/// sync itself.
+	///
+	/// example: "https://matrix.example.com"
+	pub sliding_sync_proxy: Option<Url>,
+
+	/// The identity server URL that the client well-known file will serve.
+	///
+	/// example: "https://vector.im"
+	pub identity_server: Option<Url>,
+
 	pub support_page: Option<Url>,
 
 	pub support_role: Option<ContactRole>,
@@ -2026,6 +2475,10 @@ fn default_dns_attempts() -> u16 { 10 }
 
 fn default_dns_timeout() -> u64 { 10 }
 
+fn default_dns_circuit_breaker_window() -> u64 { 60 }
+
+fn default_dns_circuit_breaker_cooldown() -> u64 { 300 }
+
 fn default_ip_lookup_strategy() -> u8 { 5 }
 
 fn default_max_request_size() -> usize {
@@ -2046,6 +2499,10 @@ fn default_well_known_conn_timeout() -> u64 { 6 }
 
 fn default_well_known_timeout() -> u64 { 10 }
 
+fn default_well_known_max_size() -> usize { 12288 }
+
+fn default_well_known_cache_max_age() -> u64 { 3600 }
+
 fn default_federation_timeout() -> u64 { 25 }
 
 fn default_federation_idle_timeout() -> u64 { 25 }
@@ -2082,6 +2539,24 @@ fn default_jaeger_filter() -> String {
 
 fn default_tracing_flame_output_path() -> String { "./tracing.folded".to_owned() }
 
+fn default_short_id_scan_sample() -> usize { 1000 }
+
+fn default_dns_prefer_family() -> String { "none".to_owned() }
+
+fn default_dns_max_addresses_per_family() -> usize { 32 }
+
+fn default_dns_min_addresses() -> usize { 1 }
+
+fn default_dns_dot_port() -> u16 { 853 }
+
+fn default_federation_default_port() -> u16 { 8448 }
+
+fn default_dns_slow_lookup_threshold_ms() -> u64 { 2000 }
+
+fn default_dns_selftest_name() -> String { "matrix.org".to_owned() }
+
+fn default_dns_quarantine_threshold() -> u32 { 3 }
+
 fn default_trusted_servers() -> Vec<OwnedServerName> {
 	vec![OwnedServerName::try_from("matrix.org").unwrap()]
 }
@@ -124,6 +124,8 @@ pub enum Error {
 	StateRes(#[from] ruma::state_res::Error),
 	#[error("uiaa")]
 	Uiaa(ruma::api::client::uiaa::UiaaInfo),
+	#[error("{0}")]
+	Unavailable(Cow<'static, str>, std::time::Duration), // 503 with Retry-After
 
 	// unique / untyped
 	#[error("{0}")]
@@ -184,11 +186,22 @@ pub fn status_code(&self) -> http::StatusCode {
 			| Self::FeatureDisabled(..) => response::bad_request_code(&self.kind()),
 			| Self::Reqwest(error) => error.status().unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
 			| Self::Conflict(_) => StatusCode::CONFLICT,
+			| Self::Unavailable(..) => StatusCode::SERVICE_UNAVAILABLE,
 			| Self::Io(error) => response::io_error_code(error.kind()),
 			| _ => StatusCode::INTERNAL_SERVER_ERROR,
 		}
 	}
 
+	/// Returns how long the client should wait before retrying, for errors
+	/// answered with a Retry-After header.
+	#[inline]
+	pub fn retry_after(&self) -> Option<std::time::Duration> {
+		match self {
+			| Self::Unavailable(_, retry_after) => Some(*retry_after),
+			| _ => None,
+		}
+	}
+
 	/// Returns true for "not found" errors. This means anything that qualifies
 	/// as a "not found" from any variant's contained error type. This call is
 	/// often used as a special case to eliminate a contained Option with a
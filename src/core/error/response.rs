@@ -1,5 +1,5 @@
 use bytes::BytesMut;
-use http::StatusCode;
+use http::{header::RETRY_AFTER, HeaderValue, StatusCode};
 use http_body_util::Full;
 use ruma::api::{
 	client::{
@@ -14,13 +14,21 @@
 
 impl axum::response::IntoResponse for Error {
 	fn into_response(self) -> axum::response::Response {
+		let retry_after = self.retry_after();
 		let response: UiaaResponse = self.into();
 		response
 			.try_into_http_response::<BytesMut>()
 			.inspect_err(|e| error!("error response error: {e}"))
 			.map_or_else(
 				|_| StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-				|r| r.map(BytesMut::freeze).map(Full::new).into_response(),
+				|mut r| {
+					if let Some(retry_after) = retry_after {
+						r.headers_mut()
+							.insert(RETRY_AFTER, HeaderValue::from(retry_after.as_secs()));
+					}
+
+					r.map(BytesMut::freeze).map(Full::new).into_response()
+				},
 			)
 	}
 }
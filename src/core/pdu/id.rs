@@ -1,10 +1,95 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
 use super::{Count, RawId};
 use crate::utils::u64_from_u8x8;
 
-pub type ShortRoomId = ShortId;
-pub type ShortEventId = ShortId;
 pub type ShortId = u64;
 
+macro_rules! short_id {
+	($(#[$meta:meta])* $name:ident) => {
+		$(#[$meta])*
+		#[derive(
+			Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+		)]
+		#[serde(transparent)]
+		pub struct $name(pub ShortId);
+
+		impl $name {
+			#[inline]
+			#[must_use]
+			pub const fn from_be_bytes(bytes: [u8; size_of::<ShortId>()]) -> Self {
+				Self(ShortId::from_be_bytes(bytes))
+			}
+
+			#[inline]
+			#[must_use]
+			pub const fn to_be_bytes(self) -> [u8; size_of::<ShortId>()] { self.0.to_be_bytes() }
+
+			#[inline]
+			#[must_use]
+			pub const fn get(self) -> ShortId { self.0 }
+		}
+
+		impl From<ShortId> for $name {
+			#[inline]
+			fn from(short: ShortId) -> Self { Self(short) }
+		}
+
+		impl From<$name> for ShortId {
+			#[inline]
+			fn from(short: $name) -> Self { short.0 }
+		}
+
+		impl From<[u8; size_of::<ShortId>()]> for $name {
+			#[inline]
+			fn from(bytes: [u8; size_of::<ShortId>()]) -> Self { Self::from_be_bytes(bytes) }
+		}
+
+		impl From<$name> for [u8; size_of::<ShortId>()] {
+			#[inline]
+			fn from(short: $name) -> Self { short.to_be_bytes() }
+		}
+
+		impl FromStr for $name {
+			type Err = std::num::ParseIntError;
+
+			#[inline]
+			fn from_str(s: &str) -> Result<Self, Self::Err> { s.parse().map(Self) }
+		}
+
+		impl fmt::Display for $name {
+			#[inline]
+			fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				fmt::Display::fmt(&self.0, f)
+			}
+		}
+	};
+}
+
+short_id! {
+	/// Short identifier of an event; keys `shorteventid_eventid`.
+	ShortEventId
+}
+
+short_id! {
+	/// Short identifier of an (event_type, state_key) pair; keys
+	/// `shortstatekey_statekey`.
+	ShortStateKey
+}
+
+short_id! {
+	/// Short identifier of a room state snapshot; keys
+	/// `shortstatehash_statediff`.
+	ShortStateHash
+}
+
+short_id! {
+	/// Short identifier of a room; prefixes its PDU IDs.
+	ShortRoomId
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Id {
 	pub shortroomid: ShortRoomId,
@@ -15,7 +100,7 @@ impl From<RawId> for Id {
 	#[inline]
 	fn from(raw: RawId) -> Self {
 		Self {
-			shortroomid: u64_from_u8x8(raw.shortroomid()),
+			shortroomid: ShortRoomId::from_be_bytes(raw.shortroomid()),
 			shorteventid: Count::from_unsigned(u64_from_u8x8(raw.shorteventid())),
 		}
 	}
@@ -1,4 +1,4 @@
-use super::Count;
+use super::{Count, ShortEventId, ShortRoomId};
 
 #[test]
 fn backfilled_parse() {
@@ -15,3 +15,20 @@ fn normal_parse() {
 
 	assert!(!backfilled, "backfilled variant");
 }
+
+#[test]
+fn short_id_bytes_roundtrip() {
+	let short = ShortEventId(0x0102_0304_0506_0708);
+	let bytes: [u8; 8] = short.into();
+
+	assert_eq!(bytes, [1, 2, 3, 4, 5, 6, 7, 8], "not big-endian");
+	assert_eq!(ShortEventId::from(bytes), short, "roundtrip mismatch");
+}
+
+#[test]
+fn short_id_parse() {
+	let short: ShortRoomId = "987654".parse().expect("parse() failed");
+
+	assert_eq!(short, ShortRoomId(987_654), "parsed value mismatch");
+	assert_eq!(short.to_string(), "987654", "display mismatch");
+}
@@ -39,6 +39,12 @@ pub fn time_from_now_secs(range: Range<u64>) -> SystemTime {
 		.expect("range does not overflow SystemTime")
 }
 
+#[must_use]
+pub fn number(range: Range<u64>) -> u64 {
+	let mut rng = thread_rng();
+	rng.gen_range(range)
+}
+
 #[must_use]
 pub fn secs(range: Range<u64>) -> Duration {
 	let mut rng = thread_rng();
@@ -269,6 +269,11 @@ pub(super) fn open_list(db: &Arc<Engine>, maps: &[Descriptor]) -> Result<Maps> {
 		key_size_hint: Some(8),
 		..descriptor::SEQUENTIAL_SMALL
 	},
+	Descriptor {
+		name: "shortstatehash_statehash",
+		key_size_hint: Some(8),
+		..descriptor::SEQUENTIAL_SMALL
+	},
 	Descriptor {
 		name: "shortstatekey_statekey",
 		cache_disp: CacheDisp::Unique,
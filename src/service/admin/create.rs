@@ -31,7 +31,7 @@ pub async fn create_admin_room(services: &Services) -> Result<()> {
 		.rooms
 		.short
 		.get_or_create_shortroomid(&room_id)
-		.await;
+		.await?;
 
 	let state_lock = services.rooms.state.mutex.lock(&room_id).await;
 
@@ -3,8 +3,10 @@
 use async_trait::async_trait;
 use conduwuit::{
 	config::{check, Config},
-	error, implement, Result, Server,
+	err, error, implement, Err, Result, Server,
 };
+use ruma::OwnedServerName;
+use url::Url;
 
 pub struct Service {
 	server: Arc<Server>,
@@ -68,3 +70,38 @@ pub fn reload<'a, I>(&self, paths: I) -> Result<Arc<Config>>
 	check::reload(&old, &new)?;
 	self.server.config.update(new)
 }
+
+/// Apply a change to the active configuration in-memory. The change is not
+/// written to the config file and is lost on the next reload or restart.
+#[implement(Service)]
+pub fn update<F>(&self, change: F) -> Result<Arc<Config>>
+where
+	F: FnOnce(&mut Config),
+{
+	let old = self.server.config.clone();
+	let mut new = Config::clone(&old);
+	change(&mut new);
+
+	check::reload(&old, &new)?;
+	self.server.config.update(new)
+}
+
+/// Set the URL served by the client well-known endpoint.
+#[implement(Service)]
+pub fn set_well_known_client(&self, url: &str) -> Result<Arc<Config>> {
+	let url: Url = url
+		.parse()
+		.map_err(|e| err!(Config("well_known.client", "Invalid URL {url:?}: {e}")))?;
+
+	if url.scheme() != "https" || url.host().is_none() {
+		return Err!(Config("well_known.client", "URL must be https with a host: {url}"));
+	}
+
+	self.update(|config| config.well_known.client = Some(url))
+}
+
+/// Set the server name served by the server well-known endpoint.
+#[implement(Service)]
+pub fn set_well_known_server(&self, server: OwnedServerName) -> Result<Arc<Config>> {
+	self.update(|config| config.well_known.server = Some(server))
+}
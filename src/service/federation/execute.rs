@@ -97,7 +97,14 @@ async fn perform<T>(
 
 	debug!(?method, ?url, "Sending request");
 	match client.execute(request).await {
-		| Ok(response) => handle_response::<T>(dest, actual, &method, &url, response).await,
+		| Ok(response) => {
+			if let (Some(host), Some(addr)) = (url.host_str(), response.remote_addr()) {
+				let server = &self.services.server;
+				self.services.resolver.hints.record(server, host, addr.ip());
+			}
+
+			handle_response::<T>(dest, actual, &method, &url, response).await
+		},
 		| Err(error) =>
 			Err(handle_error(actual, &method, &url, error).expect_err("always returns error")),
 	}
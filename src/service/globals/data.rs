@@ -1,6 +1,9 @@
-use std::sync::{Arc, RwLock};
+use std::{
+	ops::Range,
+	sync::{Arc, RwLock},
+};
 
-use conduwuit::{utils, Result};
+use conduwuit::{err, utils, warn, Result};
 use database::{Database, Deserialized, Map};
 
 pub struct Data {
@@ -11,14 +14,25 @@ pub struct Data {
 
 const COUNTER: &[u8] = b"c";
 
+/// Highest count ever allocated. Allocation is refused beyond this rather
+/// than risk the counter wrapping and colliding with existing short IDs.
+const COUNTER_CEILING: u64 = u64::MAX - (1 << 32);
+
+/// Headroom below which the counter is warned about at startup.
+const COUNTER_WARN_HEADROOM: u64 = 1 << 48;
+
 impl Data {
 	pub(super) fn new(args: &crate::Args<'_>) -> Self {
 		let db = &args.db;
+		let count = Self::stored_count(&db["global"]).expect("initialized global counter");
+		let headroom = COUNTER_CEILING.saturating_sub(count);
+		if headroom < COUNTER_WARN_HEADROOM {
+			warn!(count, headroom, "The global counter is approaching its ceiling");
+		}
+
 		Self {
 			global: db["global"].clone(),
-			counter: RwLock::new(
-				Self::stored_count(&db["global"]).expect("initialized global counter"),
-			),
+			counter: RwLock::new(count),
 			db: args.db.clone(),
 		}
 	}
@@ -32,15 +46,30 @@ pub fn next_count(&self) -> Result<u64> {
 			"counter mismatch"
 		);
 
-		*counter = counter
-			.checked_add(1)
-			.expect("counter must not overflow u64");
-
+		*counter = Self::advance(*counter, 1)?;
 		self.global.insert(COUNTER, counter.to_be_bytes());
 
 		Ok(*counter)
 	}
 
+	pub fn reserve_counts(&self, n: u64) -> Result<Range<u64>> {
+		let _cork = self.db.cork();
+		let mut lock = self.counter.write().expect("locked");
+		let counter: &mut u64 = &mut lock;
+		debug_assert!(
+			*counter == Self::stored_count(&self.global).expect("database failure"),
+			"counter mismatch"
+		);
+
+		let end = Self::advance(*counter, n)?;
+		let start = counter.saturating_add(1);
+		*counter = end;
+
+		self.global.insert(COUNTER, counter.to_be_bytes());
+
+		Ok(start..start.saturating_add(n))
+	}
+
 	#[inline]
 	pub fn current_count(&self) -> u64 {
 		let lock = self.counter.read().expect("locked");
@@ -53,6 +82,35 @@ pub fn current_count(&self) -> u64 {
 		*counter
 	}
 
+	pub fn raise_count(&self, count: u64) -> u64 {
+		let _cork = self.db.cork();
+		let mut lock = self.counter.write().expect("locked");
+		let counter: &mut u64 = &mut lock;
+		let prior = *counter;
+		if prior < count {
+			*counter = count;
+			self.global.insert(COUNTER, counter.to_be_bytes());
+		}
+
+		prior
+	}
+
+	/// Counts remaining before allocation is refused.
+	#[inline]
+	pub fn counter_headroom(&self) -> u64 { COUNTER_CEILING.saturating_sub(self.current_count()) }
+
+	/// The counter advanced by `n`, unless that would pass `COUNTER_CEILING`.
+	fn advance(counter: u64, n: u64) -> Result<u64> {
+		counter
+			.checked_add(n)
+			.filter(|advanced| *advanced <= COUNTER_CEILING)
+			.ok_or_else(|| {
+				err!(Database(error!(
+					"Refusing to allocate {n} counts beyond the counter's ceiling from {counter}"
+				)))
+			})
+	}
+
 	fn stored_count(global: &Arc<Map>) -> Result<u64> {
 		global
 			.get_blocking(COUNTER)
@@ -3,6 +3,7 @@
 use std::{
 	collections::HashMap,
 	fmt::Write,
+	ops::Range,
 	sync::{Arc, RwLock},
 	time::Instant,
 };
@@ -23,6 +24,7 @@ pub struct Service {
 	pub admin_alias: OwnedRoomAliasId,
 	pub turn_secret: String,
 	pub registration_token: Option<String>,
+	maintenance: RwLock<Option<String>>,
 }
 
 type RateLimitState = (Instant, u32); // Time if last failed try, number of failed tries
@@ -70,6 +72,7 @@ fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
 			.expect("@conduit:server_name is valid"),
 			turn_secret,
 			registration_token,
+			maintenance: RwLock::new(None),
 		}))
 	}
 
@@ -103,9 +106,34 @@ impl Service {
 	#[inline]
 	pub fn next_count(&self) -> Result<u64> { self.db.next_count() }
 
+	/// Allocate `n` consecutive counts at once, persisting the counter only
+	/// once; equivalent to calling `next_count` `n` times.
+	#[inline]
+	pub fn reserve_counts(&self, n: u64) -> Result<Range<u64>> { self.db.reserve_counts(n) }
+
 	#[inline]
 	pub fn current_count(&self) -> Result<u64> { Ok(self.db.current_count()) }
 
+	/// Counts remaining before allocation is refused to keep the counter from
+	/// overflowing.
+	#[inline]
+	pub fn counter_headroom(&self) -> u64 { self.db.counter_headroom() }
+
+	/// Raise the counter to at least `count`, returning its prior value.
+	#[inline]
+	pub fn raise_count(&self, count: u64) -> u64 { self.db.raise_count(count) }
+
+	/// The reason given for maintenance while in maintenance mode, during
+	/// which clients are asked to back off.
+	pub fn maintenance_mode(&self) -> Option<String> {
+		self.maintenance.read().expect("locked for reading").clone()
+	}
+
+	/// Enter maintenance mode with the given reason, or leave it with `None`.
+	pub fn set_maintenance_mode(&self, reason: Option<String>) {
+		*self.maintenance.write().expect("locked for writing") = reason;
+	}
+
 	#[inline]
 	pub fn server_name(&self) -> &ServerName { self.server.name.as_ref() }
 
@@ -9,7 +9,7 @@
 	},
 	warn, Err, Result,
 };
-use futures::{FutureExt, StreamExt};
+use futures::{pin_mut, FutureExt, StreamExt};
 use itertools::Itertools;
 use ruma::{
 	events::{
@@ -27,7 +27,7 @@
 /// - If database is opened at lesser version we apply migrations up to this.
 ///   Note that named-feature migrations may also be performed when opening at
 ///   equal or lesser version. These are expected to be backward-compatible.
-pub(crate) const DATABASE_VERSION: u64 = 17;
+pub(crate) const DATABASE_VERSION: u64 = 18;
 
 pub(crate) async fn migrations(services: &Services) -> Result<()> {
 	let users_count = services.users.count().await;
@@ -46,6 +46,10 @@ pub(crate) async fn migrations(services: &Services) -> Result<()> {
 	}
 
 	if users_count > 0 {
+		if services.server.config.short_id_seed_count.is_some() {
+			warn!("short_id_seed_count has no effect on an existing database");
+		}
+
 		migrate(services).await
 	} else {
 		fresh(services).await
@@ -62,6 +66,13 @@ async fn fresh(services: &Services) -> Result<()> {
 	db["global"].insert(b"retroactively_fix_bad_data_from_roomuserid_joined", []);
 	db["global"].insert(b"fix_referencedevents_missing_sep", []);
 	db["global"].insert(b"fix_readreceiptid_readreceipt_duplicates", []);
+	db["global"].insert(b"backfill_shortstatehash_statehash", []);
+
+	// Seed the counter before anything is allocated, for deterministic replay
+	if let Some(seed) = services.server.config.short_id_seed_count {
+		services.globals.raise_count(seed);
+		warn!("Seeded the global counter at {seed}; never use this with live traffic");
+	}
 
 	// Create the admin room and server user on first run
 	crate::admin::create_admin_room(services).boxed().await?;
@@ -138,6 +149,18 @@ async fn migrate(services: &Services) -> Result<()> {
 		info!("Migration: Bumped database version to 17");
 	}
 
+	if services.globals.db.database_version().await < 18 {
+		rewrite_statekey_encoding(services).await?;
+	}
+
+	if db["global"]
+		.get(b"backfill_shortstatehash_statehash")
+		.await
+		.is_not_found()
+	{
+		backfill_shortstatehash_statehash(services).await?;
+	}
+
 	assert_eq!(
 		services.globals.db.database_version().await,
 		DATABASE_VERSION,
@@ -555,3 +578,105 @@ async fn fix_readreceiptid_readreceipt_duplicates(services: &Services) -> Result
 	db["global"].insert(b"fix_readreceiptid_readreceipt_duplicates", []);
 	db.db.sort()
 }
+
+/// Rewrites the statekey tables in the length-prefixed encoding. Progress is
+/// recorded after each row so an interrupted run resumes where it stopped; a
+/// row already in the new encoding is skipped, and a row in neither encoding
+/// is logged and left as it is.
+async fn rewrite_statekey_encoding(services: &Services) -> Result {
+	use crate::rooms::short::{decode_statekey, encode_statekey};
+
+	const PROGRESS: &[u8] = b"rewrite_statekey_encoding_progress";
+
+	warn!("Rewriting statekey tables with length-prefixed encoding...");
+
+	let db = &services.db;
+	let cork = db.cork_and_sync();
+	let global = db["global"].clone();
+	let statekey_shortstatekey = db["statekey_shortstatekey"].clone();
+	let shortstatekey_statekey = db["shortstatekey_statekey"].clone();
+
+	let resume: Option<Vec<u8>> = global.get(PROGRESS).await.ok().map(|short| short.to_vec());
+	if let Some(resume) = &resume {
+		warn!(?resume, "Resuming interrupted rewrite of statekey tables");
+	}
+
+	let from = resume.clone().unwrap_or_default();
+	let rows = shortstatekey_statekey.raw_stream_from(&from);
+	pin_mut!(rows);
+
+	let (mut total, mut skipped, mut malformed) = (0_usize, 0_usize, 0_usize);
+	while let Some(row) = rows.next().await {
+		let (short, old) = row?;
+		if resume.as_deref() == Some(short) {
+			continue;
+		}
+
+		let (short, old) = (short.to_vec(), old.to_vec());
+		match split_separated_statekey(&old) {
+			| Some((event_type, state_key)) => {
+				let new = encode_statekey(&event_type.into(), state_key);
+				statekey_shortstatekey.insert(&new, &short);
+				statekey_shortstatekey.remove(&old);
+				shortstatekey_statekey.insert(&short, &new);
+				total = total.saturating_add(1);
+			},
+			| None if decode_statekey(&old).is_ok() => {
+				skipped = skipped.saturating_add(1);
+			},
+			| None => {
+				error!(?short, statekey = ?old, "Leaving malformed statekey as it is");
+				malformed = malformed.saturating_add(1);
+			},
+		}
+
+		global.insert(PROGRESS, &short);
+	}
+
+	global.remove(PROGRESS);
+	drop(cork);
+	info!(
+		total,
+		skipped, malformed, "Rewrote statekey tables with length-prefixed encoding."
+	);
+
+	services.globals.db.bump_database_version(18);
+	info!("Migration: Bumped database version to 18");
+	db.db.sort()
+}
+
+/// Splits a statekey in the former encoding, the event type and state key
+/// joined by a separator; None unless both parts are UTF-8.
+fn split_separated_statekey(statekey: &[u8]) -> Option<(&str, &str)> {
+	let pos = statekey.iter().position(|&b| b == database::SEP)?;
+	let (event_type, state_key) = statekey.split_at(pos);
+	let event_type = std::str::from_utf8(event_type).ok()?;
+	let state_key = std::str::from_utf8(state_key.get(1..)?).ok()?;
+
+	Some((event_type, state_key))
+}
+
+async fn backfill_shortstatehash_statehash(services: &Services) -> Result {
+	warn!("Backfilling shortstatehash_statehash from statehash_shortstatehash...");
+
+	let db = &services.db;
+	let cork = db.cork_and_sync();
+	let statehash_shortstatehash = db["statehash_shortstatehash"].clone();
+	let shortstatehash_statehash = db["shortstatehash_statehash"].clone();
+
+	let mut total: usize = 0;
+	statehash_shortstatehash
+		.raw_stream()
+		.expect_ok()
+		.ready_for_each(|(state_hash, short)| {
+			shortstatehash_statehash.insert(short, state_hash);
+			total = total.saturating_add(1);
+		})
+		.await;
+
+	drop(cork);
+	info!(?total, "Backfilled shortstatehash_statehash.");
+
+	db["global"].insert(b"backfill_shortstatehash_statehash", []);
+	db.db.sort()
+}
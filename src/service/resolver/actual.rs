@@ -1,17 +1,27 @@
 use std::{
 	fmt::Debug,
+	future::Future,
 	net::{IpAddr, SocketAddr},
+	sync::Arc,
+	time::Duration,
 };
 
-use conduwuit::{debug, debug_error, debug_info, debug_warn, err, error, trace, Err, Result};
+use conduwuit::{
+	debug, debug_error, debug_info, debug_warn, err, error, trace, utils::time, Err, Error,
+	Result,
+};
 use futures::{FutureExt, TryFutureExt};
 use hickory_resolver::error::ResolveError;
 use ipaddress::IPAddress;
+use reqwest::StatusCode;
 use ruma::ServerName;
 
 use super::{
 	cache::{CachedDest, CachedOverride, MAX_IPS},
-	fed::{add_port_to_hostname, get_ip_with_port, FedDest, PortString},
+	dns::order_families,
+	failure::ResolveFailure,
+	fed::{add_port_to_hostname, get_ip_with_port, hostname_with_port, FedDest, PortString},
+	metrics::QueryType,
 };
 
 #[derive(Clone, Debug)]
@@ -25,35 +35,126 @@ impl ActualDest {
 	pub(crate) fn string(&self) -> String { self.dest.https_string() }
 }
 
+/// Outcome of requesting a server's `.well-known/matrix/server`.
+#[derive(Debug)]
+pub struct WellKnown {
+	/// Status of the HTTP response, if one was received.
+	pub status: Option<StatusCode>,
+	/// The delegated server, or why the response did not yield one.
+	pub m_server: Result<String, String>,
+}
+
+impl WellKnown {
+	fn failed(status: Option<StatusCode>, reason: String) -> Self {
+		Self { status, m_server: Err(reason) }
+	}
+}
+
 impl super::Service {
 	#[tracing::instrument(skip_all, level = "debug", name = "resolve")]
-	pub(crate) async fn get_actual_dest(&self, server_name: &ServerName) -> Result<ActualDest> {
+	pub(crate) async fn get_actual_dest(
+		self: &Arc<Self>,
+		server_name: &ServerName,
+	) -> Result<ActualDest> {
 		let (CachedDest { dest, host, .. }, _cached) =
 			self.lookup_actual_dest(server_name).await?;
 
 		Ok(ActualDest { dest, host })
 	}
 
+	/// Resolves a server name to the socket addresses to connect to, in the
+	/// order they should be tried. This runs the complete server-name
+	/// resolution: IP literal, .well-known delegation, SRV records and finally
+	/// A/AAAA records of the resulting hostname. Addresses are filtered,
+	/// ordered and capped by family as the connector does, including those of
+	/// overrides and IP literals.
+	pub async fn resolve_matrix_destination(
+		self: &Arc<Self>,
+		server_name: &ServerName,
+	) -> Result<Vec<SocketAddr>> {
+		let (CachedDest { dest, .. }, _cached) = self.lookup_actual_dest(server_name).await?;
+
+		let FedDest::Named(host, _) = &dest else {
+			let disable_ipv6 = self.services.server.config.dns_disable_ipv6;
+			return Ok(dest
+				.socket_addrs(None)
+				.into_iter()
+				.filter(|addr| !(disable_ipv6 && addr.is_ipv6()))
+				.collect());
+		};
+
+		let ips = match self.cache.get_override(host).await {
+			| Ok(cached) if cached.valid() => {
+				let ips = order_families(&self.services.server, cached.ips.iter().copied());
+				if ips.is_empty() {
+					return Err!("Override for {host:?} holds no usable addresses");
+				}
+
+				ips
+			},
+			| Ok(CachedOverride { overriding: Some(overriding), .. }) =>
+				self.resolver.lookup_ips(&overriding).await?,
+			| _ => self.resolver.lookup_ips(host).await?,
+		};
+
+		Ok(dest.socket_addrs(ips))
+	}
+
 	pub(crate) async fn lookup_actual_dest(
-		&self,
+		self: &Arc<Self>,
 		server_name: &ServerName,
 	) -> Result<(CachedDest, bool)> {
 		if let Ok(result) = self.cache.get_destination(server_name).await {
 			return Ok((result, true));
 		}
 
+		let max_stale =
+			Duration::from_secs(self.services.server.config.dns_destination_max_stale);
+		if !max_stale.is_zero() {
+			if let Ok(result) = self
+				.cache
+				.get_stale_destination(server_name, max_stale)
+				.await
+			{
+				self.revalidate_dest(server_name);
+				return Ok((result, true));
+			}
+		}
+
 		let _dedup = self.resolving.lock(server_name.as_str());
 		if let Ok(result) = self.cache.get_destination(server_name).await {
 			return Ok((result, true));
 		}
 
 		self.resolve_actual_dest(server_name, true)
+			.map_err(|e| match ResolveFailure::find(&e) {
+				| Some(failure) => self.outage_error(failure, e),
+				| None => e,
+			})
 			.inspect_ok(|result| self.cache.set_destination(server_name, result))
 			.map_ok(|result| (result, false))
 			.boxed()
 			.await
 	}
 
+	/// Resolves a server name again in the background, replacing its stale
+	/// cached destination once done. Does nothing if already in progress.
+	fn revalidate_dest(self: &Arc<Self>, server_name: &ServerName) {
+		let Ok(guard) = self.revalidating.try_lock(server_name.as_str()) else {
+			return;
+		};
+
+		let self_ = Arc::clone(self);
+		let server_name = server_name.to_owned();
+		self.services.server.runtime().spawn(async move {
+			let _guard = guard;
+			match self_.resolve_actual_dest(&server_name, true).await {
+				| Ok(result) => self_.cache.set_destination(&server_name, &result),
+				| Err(e) => debug_warn!(%server_name, "Failed to revalidate destination: {e}"),
+			}
+		});
+	}
+
 	/// Returns: `actual_destination`, host header
 	/// Implemented according to the specification at <https://matrix.org/docs/spec/server_server/r0.1.4#resolving-server-names>
 	/// Numbers in comments below refer to bullet points in linked section of
@@ -73,10 +174,13 @@ pub async fn resolve_actual_dest(
 					self.actual_dest_2(dest, cache, pos).await?
 				} else if let Some(delegated) = self.request_well_known(dest.as_str()).await? {
 					self.actual_dest_3(&mut host, cache, delegated).await?
-				} else if let Some(overrider) = self.query_srv_record(dest.as_str()).await? {
-					self.actual_dest_4(&host, cache, overrider).await?
 				} else {
-					self.actual_dest_5(dest, cache).await?
+					let default_port = self.services.server.config.federation_default_port;
+					match self.query_srv_record(dest.as_str(), default_port).await? {
+						| SrvDest::Record(overrider) =>
+							self.actual_dest_4(&host, cache, overrider).await?,
+						| SrvDest::Fallback(dest) => self.actual_dest_5(dest, cache).await?,
+					}
 				},
 		};
 
@@ -136,10 +240,10 @@ async fn actual_dest_3(
 					self.actual_dest_3_2(cache, delegated, pos).await
 				} else {
 					trace!("Delegated hostname has no port in this branch");
-					if let Some(overrider) = self.query_srv_record(&delegated).await? {
-						self.actual_dest_3_3(cache, delegated, overrider).await
-					} else {
-						self.actual_dest_3_4(cache, delegated).await
+					match self.query_srv_record(&delegated, 8448).await? {
+						| SrvDest::Record(overrider) =>
+							self.actual_dest_3_3(cache, delegated, overrider).await,
+						| SrvDest::Fallback(_) => self.actual_dest_3_4(cache, delegated).await,
 					}
 				},
 		}
@@ -231,16 +335,24 @@ async fn actual_dest_4(
 		Ok(add_port_to_hostname(host))
 	}
 
-	async fn actual_dest_5(&self, dest: &ServerName, cache: bool) -> Result<FedDest> {
+	async fn actual_dest_5(&self, dest: FedDest, cache: bool) -> Result<FedDest> {
 		debug!("5: No SRV record found");
-		self.conditional_query_and_cache(dest.as_str(), 8448, cache)
+		let port = dest.port().unwrap_or(8448);
+		self.conditional_query_and_cache(&dest.hostname(), port, cache)
 			.await?;
 
-		Ok(add_port_to_hostname(dest.as_str()))
+		Ok(dest)
+	}
+
+	pub async fn request_well_known(&self, dest: &str) -> Result<Option<String>> {
+		let WellKnown { m_server, .. } = self.query_well_known(dest).await?;
+		Ok(m_server.ok())
 	}
 
+	/// Requests `.well-known/matrix/server` from `dest`, reporting the HTTP
+	/// status and why no usable `m.server` was found alongside the result.
 	#[tracing::instrument(name = "well-known", level = "debug", skip(self, dest))]
-	async fn request_well_known(&self, dest: &str) -> Result<Option<String>> {
+	pub async fn query_well_known(&self, dest: &str) -> Result<WellKnown> {
 		self.conditional_query_and_cache(dest, 8448, true).await?;
 
 		self.services.server.check_running()?;
@@ -254,24 +366,44 @@ async fn request_well_known(&self, dest: &str) -> Result<Option<String>> {
 			.await;
 
 		trace!("response: {response:?}");
-		if let Err(e) = &response {
-			debug!("error: {e:?}");
-			return Ok(None);
-		}
+		let mut response = match response {
+			| Ok(response) => response,
+			| Err(e) => {
+				debug!("error: {e:?}");
+				return Ok(WellKnown::failed(None, format!("request failed: {e}")));
+			},
+		};
 
-		let response = response?;
+		let status = Some(response.status());
 		if !response.status().is_success() {
 			debug!("response not 2XX");
-			return Ok(None);
+			return Ok(WellKnown::failed(status, "response not 2XX".into()));
 		}
 
-		let text = response.text().await?;
-		trace!("response text: {text:?}");
-		if text.len() >= 12288 {
-			debug_warn!("response contains junk");
-			return Ok(None);
+		let max_size = self.services.server.config.well_known_max_size;
+		let oversized = |len: usize| len > max_size;
+		let too_large = || format!("response exceeds well_known_max_size ({max_size} bytes)");
+		if response
+			.content_length()
+			.is_some_and(|len| usize::try_from(len).ok().is_none_or(oversized))
+		{
+			debug_warn!("{}", too_large());
+			return Ok(WellKnown::failed(status, too_large()));
+		}
+
+		let mut bytes: Vec<u8> = Vec::new();
+		while let Some(chunk) = response.chunk().await? {
+			if oversized(bytes.len().saturating_add(chunk.len())) {
+				debug_warn!("{}", too_large());
+				return Ok(WellKnown::failed(status, too_large()));
+			}
+
+			bytes.extend_from_slice(&chunk);
 		}
 
+		let text = String::from_utf8_lossy(&bytes);
+		trace!("response text: {text:?}");
+
 		let body: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
 
 		let m_server = body
@@ -280,13 +412,21 @@ async fn request_well_known(&self, dest: &str) -> Result<Option<String>> {
 			.as_str()
 			.unwrap_or_default();
 
-		if ruma::identifiers_validation::server_name::validate(m_server).is_err() {
-			debug_error!("response content missing or invalid");
-			return Ok(None);
+		if m_server.is_empty() {
+			debug_error!("response content missing m.server");
+			return Ok(WellKnown::failed(status, "m.server missing".into()));
+		}
+
+		if let Err(e) = ruma::identifiers_validation::server_name::validate(m_server) {
+			debug_error!("response content invalid");
+			return Ok(WellKnown::failed(status, format!("m.server {m_server:?} invalid: {e}")));
 		}
 
 		debug_info!("{dest:?} found at {m_server:?}");
-		Ok(Some(m_server.to_owned()))
+		Ok(WellKnown {
+			status,
+			m_server: Ok(m_server.to_owned()),
+		})
 	}
 
 	#[inline]
@@ -330,8 +470,19 @@ async fn query_and_cache_override(
 		self.services.server.check_running()?;
 
 		debug!("querying IP for {untername:?} ({hostname:?}:{port})");
-		match self.resolver.resolver.lookup_ip(hostname.to_owned()).await {
-			| Err(e) => Self::handle_resolve_error(&e, hostname),
+		let (resolver, strategy) = self.resolver.resolver_for(hostname);
+		self.metrics.record_ip(strategy);
+		let mut result = resolver.lookup_ip(hostname.to_owned()).await;
+		if let (Err(_), Some(fallback)) = (&result, self.resolver.fallback.as_ref()) {
+			self.metrics
+				.record_ip(self.services.server.config.ip_lookup_strategy);
+			result = fallback.lookup_ip(hostname.to_owned()).await;
+		}
+
+		self.metrics.record_outcome(&result);
+
+		match result {
+			| Err(e) => self.handle_resolve_error(&e, hostname),
 			| Ok(override_ip) => {
 				self.cache.set_override(untername, &CachedOverride {
 					ips: override_ip.into_iter().take(MAX_IPS).collect(),
@@ -340,6 +491,7 @@ async fn query_and_cache_override(
 					overriding: (hostname != untername)
 						.then_some(hostname.into())
 						.inspect(|_| debug_info!("{untername:?} overriden by {hostname:?}")),
+					expires_at: None,
 				});
 
 				Ok(())
@@ -348,37 +500,74 @@ async fn query_and_cache_override(
 	}
 
 	#[tracing::instrument(name = "srv", level = "debug", skip(self))]
-	async fn query_srv_record(&self, hostname: &'_ str) -> Result<Option<FedDest>> {
-		let hostnames =
-			[format!("_matrix-fed._tcp.{hostname}."), format!("_matrix._tcp.{hostname}.")];
-
-		for hostname in hostnames {
-			self.services.server.check_running()?;
+	async fn query_srv_record(&self, hostname: &'_ str, default_port: u16) -> Result<SrvDest> {
+		self.services.server.check_running()?;
 
+		let lookup = |hostname: String| async move {
 			debug!("querying SRV for {hostname:?}");
-			let hostname = hostname.trim_end_matches('.');
-			match self.resolver.resolver.srv_lookup(hostname).await {
-				| Err(e) => Self::handle_resolve_error(&e, hostname)?,
-				| Ok(result) =>
-					return Ok(result.iter().next().map(|result| {
-						FedDest::Named(
-							result.target().to_string().trim_end_matches('.').to_owned(),
-							format!(":{}", result.port())
-								.as_str()
-								.try_into()
-								.unwrap_or_else(|_| FedDest::default_port()),
-						)
-					})),
+			self.metrics.record(QueryType::Srv);
+			let mut result = self
+				.resolver
+				.srv_resolver_for(&hostname)
+				.srv_lookup(hostname.as_str())
+				.await;
+			if let (Err(_), Some(fallback)) = (&result, self.resolver.fallback.as_ref()) {
+				self.metrics.record(QueryType::Srv);
+				result = fallback.srv_lookup(hostname.as_str()).await;
 			}
+
+			self.metrics.record_outcome(&result);
+
+			result.map(|result| {
+				result.iter().next().map(|result| {
+					FedDest::Named(
+						result.target().to_string().trim_end_matches('.').to_owned(),
+						format!(":{}", result.port())
+							.as_str()
+							.try_into()
+							.unwrap_or_else(|_| FedDest::default_port()),
+					)
+				})
+			})
+		};
+
+		let e = match walk_srv(hostname, default_port, lookup).await {
+			| Ok(dest) => return Ok(dest),
+			| Err(e) => e,
+		};
+
+		match ResolveFailure::classify(&e) {
+			| failure @ ResolveFailure::ServFail => {
+				let error = err!(warn!(%hostname, "SRV lookup failed: {e}"));
+				Err(self.outage_error(failure, error))
+			},
+			| _ => self
+				.handle_resolve_error(&e, hostname)
+				.map(|()| SrvDest::Fallback(hostname_with_port(hostname, default_port))),
 		}
+	}
 
-		Ok(None)
+	/// The error reported in place of a resolution failed for want of a
+	/// reachable nameserver while DNS is down, telling the requester to retry
+	/// later rather than reporting a server error. Names which do not exist or
+	/// are invalid still fail as such.
+	fn outage_error(&self, failure: ResolveFailure, error: Error) -> Error {
+		const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+		match self.metrics.outage() {
+			| Some(outage) if failure.is_unreachable() => Error::Unavailable(
+				format!("DNS resolution has been unavailable for {}.", time::pretty(outage))
+					.into(),
+				RETRY_AFTER,
+			),
+			| _ => error,
+		}
 	}
 
-	fn handle_resolve_error(e: &ResolveError, host: &'_ str) -> Result<()> {
+	fn handle_resolve_error(&self, e: &ResolveError, host: &'_ str) -> Result<()> {
 		use hickory_resolver::error::ResolveErrorKind;
 
-		match *e.kind() {
+		let result = match *e.kind() {
 			| ResolveErrorKind::NoRecordsFound { .. } => {
 				// Raise to debug_warn if we can find out the result wasn't from cache
 				debug!(%host, "No DNS records found: {e}");
@@ -397,7 +586,9 @@ fn handle_resolve_error(e: &ResolveError, host: &'_ str) -> Result<()> {
 				Err!(error!(%host, "DNS error: {e}"))
 			},
 			| _ => Err!(error!(%host, "DNS error: {e}")),
-		}
+		};
+
+		result.map_err(|error| self.outage_error(ResolveFailure::classify(e), error))
 	}
 
 	fn validate_dest(&self, dest: &ServerName) -> Result<()> {
@@ -435,3 +626,42 @@ pub(crate) fn validate_ip(&self, ip: &IPAddress) -> Result<()> {
 		Ok(())
 	}
 }
+
+/// Where the SRV records of a name without an explicit port direct federation.
+#[derive(Debug, Eq, PartialEq)]
+pub(super) enum SrvDest {
+	/// A record of either form names the destination.
+	Record(FedDest),
+
+	/// Neither form has a record, so the name itself is contacted on the
+	/// default port.
+	Fallback(FedDest),
+}
+
+/// Looks up the SRV record of each form of `hostname` in turn through
+/// `lookup`. Only an answer that a form has no record moves on to the next
+/// form, and after both to the name itself on `default_port`; any other
+/// failure, such as SERVFAIL from a failing nameserver, confirms nothing and
+/// fails resolution.
+pub(super) async fn walk_srv<F, Fut>(
+	hostname: &str,
+	default_port: u16,
+	mut lookup: F,
+) -> Result<SrvDest, ResolveError>
+where
+	F: FnMut(String) -> Fut,
+	Fut: Future<Output = Result<Option<FedDest>, ResolveError>>,
+{
+	for form in [format!("_matrix-fed._tcp.{hostname}"), format!("_matrix._tcp.{hostname}")] {
+		match lookup(form).await {
+			| Ok(Some(record)) => return Ok(SrvDest::Record(record)),
+			| Ok(None) => {},
+			| Err(e) if ResolveFailure::classify(&e).is_permanent() => {
+				debug!(%hostname, "No SRV records found: {e}");
+			},
+			| Err(e) => return Err(e),
+		}
+	}
+
+	Ok(SrvDest::Fallback(hostname_with_port(hostname, default_port)))
+}
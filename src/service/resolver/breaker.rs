@@ -0,0 +1,157 @@
+use std::{
+	collections::HashMap,
+	io,
+	sync::RwLock,
+	time::{Duration, Instant},
+};
+
+use conduwuit::{debug_warn, implement, utils::math::Expected, Server};
+
+/// Circuit breaker short-circuiting resolution of names which repeatedly
+/// failed to resolve, until a cooldown elapses.
+#[derive(Default)]
+pub struct Breaker {
+	states: RwLock<HashMap<String, Circuit>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Circuit {
+	/// Consecutive failures within the current window.
+	pub failures: u32,
+
+	/// Time of the first failure of the current window.
+	pub since: Instant,
+
+	/// When open, the time after which another attempt is permitted. Once
+	/// passed the circuit is half-open until the outcome of a probe.
+	pub open_until: Option<Instant>,
+
+	/// When half-open, the time the probe in flight was permitted.
+	pub probing: Option<Instant>,
+}
+
+impl Circuit {
+	pub(super) fn new(now: Instant) -> Self {
+		Self {
+			failures: 0,
+			since: now,
+			open_until: None,
+			probing: None,
+		}
+	}
+
+	/// Whether an attempt is permitted at `now`. A half-open circuit permits a
+	/// single probe at a time; another is permitted only once the probe has
+	/// been in flight for a whole cooldown, in case its outcome was lost.
+	pub(super) fn permit(&mut self, now: Instant, cooldown: Duration) -> bool {
+		match (self.open_until, self.probing) {
+			| (None, _) => true,
+			| (Some(open_until), _) if open_until > now => false,
+			| (Some(_), Some(probing)) if now.saturating_duration_since(probing) < cooldown =>
+				false,
+			| (Some(_), _) => {
+				self.probing = Some(now);
+				true
+			},
+		}
+	}
+
+	/// Count a failure at `now`, returning whether it opened the circuit. A
+	/// failed probe of a half-open circuit re-opens it at once, however long
+	/// ago the window started.
+	pub(super) fn fail(
+		&mut self,
+		now: Instant,
+		threshold: u32,
+		window: Duration,
+		cooldown: Duration,
+	) -> bool {
+		let half_open = self.open_until.is_some();
+		if !half_open && now.saturating_duration_since(self.since) > window {
+			self.failures = 0;
+			self.since = now;
+		}
+
+		self.failures = self.failures.expected_add(1);
+		if !half_open && self.failures < threshold {
+			return false;
+		}
+
+		self.open_until = now.checked_add(cooldown);
+		self.probing = None;
+		true
+	}
+}
+
+/// Check whether resolution of `name` is permitted. Once the cooldown of an
+/// open circuit elapses a single probe is permitted at a time; its failure
+/// re-opens the circuit immediately and its success closes it.
+#[implement(Breaker)]
+pub(super) fn check(&self, server: &Server, name: &str) -> io::Result<()> {
+	let cooldown = Duration::from_secs(server.config.dns_circuit_breaker_cooldown);
+	let mut states = self.states.write().expect("locked for writing");
+	match states.get_mut(name) {
+		| Some(circuit) if !circuit.permit(Instant::now(), cooldown) => Err(io::Error::new(
+			io::ErrorKind::ConnectionRefused,
+			format!("Circuit open for {name:?} after repeated resolution failures"),
+		)),
+		| _ => Ok(()),
+	}
+}
+
+/// Record the outcome of a resolution of `name`.
+#[implement(Breaker)]
+pub(super) fn record(&self, server: &Server, name: &str, success: bool) {
+	let config = &server.config;
+	let threshold = config.dns_circuit_breaker_threshold;
+	if threshold == 0 {
+		return;
+	}
+
+	let mut states = self.states.write().expect("locked for writing");
+	if success {
+		states.remove(name);
+		return;
+	}
+
+	let now = Instant::now();
+	let window = Duration::from_secs(config.dns_circuit_breaker_window);
+	let cooldown = Duration::from_secs(config.dns_circuit_breaker_cooldown);
+	let circuit = states
+		.entry(name.to_owned())
+		.or_insert_with(|| Circuit::new(now));
+
+	if circuit.fail(now, threshold, window, cooldown) {
+		debug_warn!(?name, failures = circuit.failures, "Opened DNS circuit breaker");
+	}
+}
+
+/// Names with a currently open circuit.
+#[implement(Breaker)]
+#[must_use]
+pub fn open(&self) -> Vec<(String, Circuit)> {
+	let now = Instant::now();
+	self.states
+		.read()
+		.expect("locked for reading")
+		.iter()
+		.filter(|(_, circuit)| circuit.open_until.is_some_and(|until| until > now))
+		.map(|(name, circuit)| (name.clone(), *circuit))
+		.collect()
+}
+
+/// Whether resolution of `name` is currently refused.
+#[implement(Breaker)]
+#[must_use]
+pub fn is_open(&self, name: &str) -> bool {
+	let now = Instant::now();
+	self.states
+		.read()
+		.expect("locked for reading")
+		.get(name)
+		.and_then(|circuit| circuit.open_until)
+		.is_some_and(|until| until > now)
+}
+
+#[implement(Breaker)]
+pub fn clear(&self) { self.states.write().expect("locked for writing").clear(); }
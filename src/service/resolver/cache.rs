@@ -1,10 +1,15 @@
-use std::{net::IpAddr, sync::Arc, time::SystemTime};
+use std::{
+	borrow::Cow,
+	net::IpAddr,
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
 
 use arrayvec::ArrayVec;
 use conduwuit::{
 	at, err, implement,
 	utils::{math::Expected, rand, stream::TryIgnore},
-	Result,
+	Err, Result,
 };
 use database::{Cbor, Deserialized, Map};
 use futures::{Stream, StreamExt};
@@ -31,10 +36,16 @@ pub struct CachedOverride {
 	pub port: u16,
 	pub expire: SystemTime,
 	pub overriding: Option<String>,
+	/// Removes the override entirely once passed, after which the name is
+	/// resolved normally again. Set for overrides pinned by an administrator;
+	/// like every override these are persisted in `servername_override`, so
+	/// pins and their expiry survive restarts.
+	#[serde(default)]
+	pub expires_at: Option<SystemTime>,
 }
 
 pub type IpAddrs = ArrayVec<IpAddr, MAX_IPS>;
-pub(crate) const MAX_IPS: usize = 3;
+pub const MAX_IPS: usize = 3;
 
 impl Cache {
 	pub(super) fn new(args: &crate::Args<'_>) -> Arc<Self> {
@@ -52,9 +63,13 @@ pub fn set_destination(&self, name: &ServerName, dest: &CachedDest) {
 
 #[implement(Cache)]
 pub fn set_override(&self, name: &str, over: &CachedOverride) {
-	self.overrides.raw_put(name, Cbor(over));
+	self.overrides
+		.raw_put(override_key(name).as_bytes(), Cbor(over));
 }
 
+#[implement(Cache)]
+pub fn del_override(&self, name: &str) { self.overrides.remove(override_key(name).as_bytes()); }
+
 #[implement(Cache)]
 #[must_use]
 pub async fn has_destination(&self, destination: &ServerName) -> bool {
@@ -82,13 +97,45 @@ pub async fn get_destination(&self, name: &ServerName) -> Result<CachedDest> {
 		.ok_or(err!(Request(NotFound("Expired from cache"))))
 }
 
+/// The cached destination regardless of expiry, provided it expired no more
+/// than `max_stale` ago.
 #[implement(Cache)]
-pub async fn get_override(&self, name: &str) -> Result<CachedOverride> {
-	self.overrides
+pub async fn get_stale_destination(
+	&self,
+	name: &ServerName,
+	max_stale: Duration,
+) -> Result<CachedDest> {
+	let now = SystemTime::now();
+	self.destinations
 		.get(name)
 		.await
 		.deserialized::<Cbor<_>>()
 		.map(at!(0))
+		.into_iter()
+		.find(|cached: &CachedDest| {
+			cached
+				.expire
+				.checked_add(max_stale)
+				.is_some_and(|stale| stale > now)
+		})
+		.ok_or(err!(Request(NotFound("Expired from cache"))))
+}
+
+#[implement(Cache)]
+pub async fn get_override(&self, name: &str) -> Result<CachedOverride> {
+	let cached: CachedOverride = self
+		.overrides
+		.get(override_key(name).as_bytes())
+		.await
+		.deserialized::<Cbor<_>>()
+		.map(at!(0))?;
+
+	if cached.expired() {
+		self.del_override(name);
+		return Err!(Request(NotFound("Override expired")));
+	}
+
+	Ok(cached)
 }
 
 #[implement(Cache)]
@@ -107,6 +154,16 @@ pub fn overrides(&self) -> impl Stream<Item = (&ServerName, CachedOverride)> + S
 		.map(|item: (&ServerName, Cbor<_>)| (item.0, item.1 .0))
 }
 
+/// Overrides are keyed by the lowercased name, as DNS names compare
+/// case-insensitively.
+pub(super) fn override_key(name: &str) -> Cow<'_, str> {
+	if name.bytes().any(|b| b.is_ascii_uppercase()) {
+		Cow::Owned(name.to_ascii_lowercase())
+	} else {
+		Cow::Borrowed(name)
+	}
+}
+
 impl CachedDest {
 	#[inline]
 	#[must_use]
@@ -132,6 +189,14 @@ impl CachedOverride {
 	#[must_use]
 	pub fn valid(&self) -> bool { self.expire > SystemTime::now() }
 
+	/// Whether the override has passed its `expires_at` and must be removed.
+	#[inline]
+	#[must_use]
+	pub fn expired(&self) -> bool {
+		self.expires_at
+			.is_some_and(|expires_at| expires_at <= SystemTime::now())
+	}
+
 	#[must_use]
 	pub(crate) fn default_expire() -> SystemTime {
 		rand::time_from_now_secs(60 * 60 * 6..60 * 60 * 12)
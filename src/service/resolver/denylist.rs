@@ -0,0 +1,84 @@
+use std::{collections::HashSet, io, sync::RwLock};
+
+use conduwuit::{implement, Server};
+
+use super::normalize_host;
+
+/// Hostnames the resolver refuses to resolve, seeded from `hostname_denylist`
+/// and editable at runtime.
+#[derive(Default)]
+pub struct Denylist {
+	names: RwLock<HashSet<String>>,
+}
+
+#[implement(Denylist)]
+pub(super) fn new(server: &Server) -> Self {
+	Self {
+		names: RwLock::new(
+			server
+				.config
+				.hostname_denylist
+				.iter()
+				.map(String::as_str)
+				.map(normalize_host)
+				.collect(),
+		),
+	}
+}
+
+/// Check whether resolution of `name` is permitted.
+#[implement(Denylist)]
+pub(super) fn check(&self, name: &str) -> io::Result<()> {
+	if self.is_denied(name) {
+		return Err(io::Error::new(
+			io::ErrorKind::PermissionDenied,
+			format!("Resolution of {name:?} is denied by hostname_denylist"),
+		));
+	}
+
+	Ok(())
+}
+
+/// Whether resolution of `name` is refused.
+#[implement(Denylist)]
+#[must_use]
+pub fn is_denied(&self, name: &str) -> bool {
+	self.names
+		.read()
+		.expect("locked for reading")
+		.contains(&normalize_host(name))
+}
+
+/// Refuse resolution of `name`; returns false if it was already denied.
+#[implement(Denylist)]
+pub fn add(&self, name: &str) -> bool {
+	self.names
+		.write()
+		.expect("locked for writing")
+		.insert(normalize_host(name))
+}
+
+/// Permit resolution of `name` again; returns false if it was not denied.
+#[implement(Denylist)]
+pub fn remove(&self, name: &str) -> bool {
+	self.names
+		.write()
+		.expect("locked for writing")
+		.remove(&normalize_host(name))
+}
+
+/// Currently denied hostnames, sorted.
+#[implement(Denylist)]
+#[must_use]
+pub fn names(&self) -> Vec<String> {
+	let mut names: Vec<_> = self
+		.names
+		.read()
+		.expect("locked for reading")
+		.iter()
+		.cloned()
+		.collect();
+
+	names.sort_unstable();
+	names
+}
@@ -1,34 +1,151 @@
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{
+	cmp::Reverse,
+	collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+	io,
+	net::{IpAddr, SocketAddr},
+	sync::{Arc, RwLock},
+	time::{Duration, Instant},
+};
 
-use conduwuit::{err, Result, Server};
-use futures::FutureExt;
-use hickory_resolver::{lookup_ip::LookupIp, TokioAsyncResolver};
+use conduwuit::{debug, debug_warn, err, info, Result, Server};
+use futures::{future, FutureExt};
+use hickory_resolver::{
+	config::{LookupIpStrategy, NameServerConfig, ResolverConfig, ResolverOpts},
+	error::ResolveErrorKind,
+	lookup_ip::LookupIp,
+	TokioAsyncResolver,
+};
 use reqwest::dns::{Addrs, Name, Resolve, Resolving};
 
-use super::cache::{Cache, CachedOverride};
+#[cfg(any(test, feature = "mock_resolver"))]
+use super::mock::{lookup, mock_to_reqwest, Mock};
+use super::{
+	breaker::Breaker,
+	cache::{Cache, CachedOverride},
+	denylist::Denylist,
+	hints::Hints,
+	metrics::{Metrics, QueryType},
+	normalize_host,
+};
 
 pub struct Resolver {
-	pub(crate) resolver: Arc<TokioAsyncResolver>,
+	pub(crate) fallback: Fallback,
 	pub(crate) hooked: Arc<Hooked>,
+	active: Active,
+	zones: Arc<Zones>,
 	server: Arc<Server>,
+	#[cfg(any(test, feature = "mock_resolver"))]
+	mock: Option<Arc<Mock>>,
+	denylist: Arc<Denylist>,
+	metrics: Arc<Metrics>,
+	nameservers: Nameservers,
+
+	/// What the pool is built from, retained to rebuild it.
+	conf: ResolverConfig,
+	opts: ResolverOpts,
 }
 
 pub(crate) struct Hooked {
-	resolver: Arc<TokioAsyncResolver>,
+	active: Active,
+	fallback: Fallback,
 	cache: Arc<Cache>,
 	server: Arc<Server>,
+	#[cfg(any(test, feature = "mock_resolver"))]
+	mock: Option<Arc<Mock>>,
+	floor: Arc<Floor>,
+	audited: Arc<Audited>,
+	breaker: Arc<Breaker>,
+	denylist: Arc<Denylist>,
+	hints: Arc<Hints>,
+	metrics: Arc<Metrics>,
+	zones: Arc<Zones>,
+}
+
+/// The default resolver and those applying the `ip_lookup_strategy_rules`,
+/// all querying the upstream nameservers outside of quarantine.
+struct Pool {
+	resolver: Arc<TokioAsyncResolver>,
+	rules: Rules,
+
+	/// Resolvers for SRV and TXT queries; the default resolver unless
+	/// `dns_tcp_fallback_by_query` sets them apart.
+	srv: Arc<TokioAsyncResolver>,
+	txt: Arc<TokioAsyncResolver>,
 }
 
+/// The pool currently in use, replaced whenever a nameserver enters or leaves
+/// quarantine.
+type Active = Arc<RwLock<Arc<Pool>>>;
+
+/// When the use of each name's override was last logged, limiting the audit
+/// log to one entry per name every `AUDIT_INTERVAL`.
+type Audited = RwLock<HashMap<String, Instant>>;
+
+const AUDIT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Addresses retained by the hooked resolver until the configured TTL floor
+/// elapses, keyed by name and the `ip_lookup_strategy` they were looked up
+/// with, so lookups under one strategy never return another's results.
+type Floor = RwLock<HashMap<(String, u8), (Instant, Vec<IpAddr>)>>;
+
+/// Resolvers applying the `ip_lookup_strategy_rules`, keyed by pattern along
+/// with the strategy applied and ordered most specific first.
+type Rules = Vec<(String, u8, Arc<TokioAsyncResolver>)>;
+
+/// Resolvers forwarding queries for names within each zone of
+/// `dns_forward_zones`, keyed by zone and ordered most specific first.
+type Zones = Vec<(String, Arc<TokioAsyncResolver>)>;
+
+/// Resolver consulted only when the primary resolution fails; configured by
+/// `dns_fallback_nameservers`.
+type Fallback = Option<Arc<TokioAsyncResolver>>;
+
+/// Resolvers each querying a single upstream nameserver, keyed by its address
+/// and protocol; used to probe the upstreams individually.
+type Nameservers = Vec<(String, Arc<TokioAsyncResolver>)>;
+
 type ResolvingResult = Result<Addrs, Box<dyn std::error::Error + Send + Sync>>;
 
 impl Resolver {
 	#[allow(clippy::as_conversions, clippy::cast_sign_loss, clippy::cast_possible_truncation)]
-	pub(super) fn build(server: &Arc<Server>, cache: Arc<Cache>) -> Result<Arc<Self>> {
+	pub(super) fn build(
+		server: &Arc<Server>,
+		cache: Arc<Cache>,
+		breaker: Arc<Breaker>,
+		denylist: Arc<Denylist>,
+		hints: Arc<Hints>,
+		metrics: Arc<Metrics>,
+	) -> Result<Arc<Self>> {
+		use hickory_resolver::config::Protocol;
+
 		let config = &server.config;
-		let (sys_conf, mut opts) = hickory_resolver::system_conf::read_system_conf()
-			.map_err(|e| err!(error!("Failed to configure DNS resolver from system: {e}")))?;
+		let dot = config
+			.dns_dot_upstream
+			.as_ref()
+			.zip(config.dns_dot_bootstrap_ip);
+
+		let (sys_conf, mut opts) = if let Some((upstream, ip)) = dot {
+			let addr = SocketAddr::new(ip, config.dns_dot_port);
+			let mut ns = NameServerConfig::new(addr, Protocol::Tls);
+			ns.tls_dns_name = Some(upstream.clone());
+
+			let mut conf = ResolverConfig::new();
+			conf.add_name_server(ns);
+
+			(conf, ResolverOpts::default())
+		} else if config.dns_use_system_conf {
+			hickory_resolver::system_conf::read_system_conf()
+				.map_err(|e| err!(error!("Failed to configure DNS resolver from system: {e}")))?
+		} else {
+			let mut conf = ResolverConfig::new();
+			for addr in &config.dns_nameservers {
+				conf.add_name_server(NameServerConfig::new(*addr, Protocol::Udp));
+			}
+
+			(conf, ResolverOpts::default())
+		};
 
-		let mut conf = hickory_resolver::config::ResolverConfig::new();
+		let mut conf = ResolverConfig::new();
 
 		if let Some(domain) = sys_conf.domain() {
 			conf.set_domain(domain.clone());
@@ -41,8 +158,8 @@ pub(super) fn build(server: &Arc<Server>, cache: Arc<Cache>) -> Result<Arc<Self>
 		for sys_conf in sys_conf.name_servers() {
 			let mut ns = sys_conf.clone();
 
-			if config.query_over_tcp_only {
-				ns.protocol = hickory_resolver::config::Protocol::Tcp;
+			if config.query_over_tcp_only && ns.protocol == Protocol::Udp {
+				ns.protocol = Protocol::Tcp;
 			}
 
 			ns.trust_negative_responses = !config.query_all_nameservers;
@@ -58,38 +175,401 @@ pub(super) fn build(server: &Arc<Server>, cache: Arc<Cache>) -> Result<Arc<Self>
 		opts.timeout = Duration::from_secs(config.dns_timeout);
 		opts.attempts = config.dns_attempts as usize;
 		opts.try_tcp_on_error = config.dns_tcp_fallback;
+		opts.ndots = config.dns_ndots.unwrap_or(opts.ndots);
 		opts.num_concurrent_reqs = 1;
+		// The advertised EDNS UDP payload size is fixed by hickory (1232 bytes, per
+		// DNS Flag Day 2020) and not exposed through ResolverOpts; truncated
+		// responses are retried over TCP, see `dns_tcp_fallback`.
+		// Likewise hickory offers no way to attach EDNS options such as Client
+		// Subnet to the queries it builds, so no ECS is ever sent.
+		// Query IDs and UDP source ports are always randomized by hickory using
+		// rand's thread-local CSPRNG, which is not configurable. Together these
+		// give an off-path attacker roughly 32 bits to guess per query when
+		// forging responses to poison the cache (the Kaminsky attack); DNSSEC
+		// validation is the defence against on-path attackers.
 		opts.edns0 = true;
-		opts.shuffle_dns_servers = true;
-		opts.rotate = true;
-		opts.ip_strategy = match config.ip_lookup_strategy {
-			| 1 => hickory_resolver::config::LookupIpStrategy::Ipv4Only,
-			| 2 => hickory_resolver::config::LookupIpStrategy::Ipv6Only,
-			| 3 => hickory_resolver::config::LookupIpStrategy::Ipv4AndIpv6,
-			| 4 => hickory_resolver::config::LookupIpStrategy::Ipv6thenIpv4,
-			| _ => hickory_resolver::config::LookupIpStrategy::Ipv4thenIpv6,
+		opts.shuffle_dns_servers = config.dns_shuffle_servers;
+		opts.rotate = config.dns_rotate;
+		opts.ip_strategy = ip_strategy(config.ip_lookup_strategy);
+		opts.authentic_data = config.dns_trust_ad_bit;
+		opts.recursion_desired = config.dns_recursion_desired;
+
+		let nameservers: Nameservers = conf
+			.name_servers()
+			.iter()
+			.map(|ns| {
+				let mut opts = opts.clone();
+				opts.cache_size = 0;
+				let single = ResolverConfig::from_parts(
+					conf.domain().cloned(),
+					conf.search().to_vec(),
+					vec![ns.clone()],
+				);
+
+				(nameserver_key(ns), Arc::new(TokioAsyncResolver::tokio(single, opts)))
+			})
+			.collect();
+
+		let protocol = if config.query_over_tcp_only {
+			Protocol::Tcp
+		} else {
+			Protocol::Udp
 		};
-		opts.authentic_data = false;
 
-		let resolver = Arc::new(TokioAsyncResolver::tokio(conf, opts));
-		Ok(Arc::new(Self {
-			resolver: resolver.clone(),
-			hooked: Arc::new(Hooked { resolver, cache, server: server.clone() }),
+		let forwarding = |nameservers: &[SocketAddr]| {
+			let mut conf = ResolverConfig::new();
+			for addr in nameservers {
+				conf.add_name_server(NameServerConfig::new(*addr, protocol));
+			}
+
+			Arc::new(TokioAsyncResolver::tokio(conf, opts.clone()))
+		};
+
+		let fallback: Fallback = (!config.dns_fallback_nameservers.is_empty())
+			.then(|| forwarding(&config.dns_fallback_nameservers));
+
+		let mut zones: Zones = config
+			.dns_forward_zones
+			.iter()
+			.map(|(zone, nameservers)| {
+				(zone.trim_end_matches('.').to_ascii_lowercase(), forwarding(nameservers))
+			})
+			.collect();
+
+		zones.sort_by_key(|(zone, _)| Reverse(zone.len()));
+
+		Ok(Self::new(
+			server,
+			cache,
+			breaker,
+			denylist,
+			hints,
+			metrics,
+			conf,
+			opts,
+			fallback,
+			zones,
+			nameservers,
+			#[cfg(any(test, feature = "mock_resolver"))]
+			None,
+		))
+	}
+
+	/// Construct a resolver serving exclusively from a static table of
+	/// addresses; hickory is never queried. Names missing from the table fail
+	/// to resolve. This is intended for tests routing federation to a local
+	/// mock server.
+	#[cfg(any(test, feature = "mock_resolver"))]
+	#[must_use]
+	pub fn mock(server: &Arc<Server>, cache: Arc<Cache>, mock: Mock) -> Arc<Self> {
+		Self::new(
+			server,
+			cache,
+			Arc::default(),
+			Arc::default(),
+			Arc::default(),
+			Arc::default(),
+			ResolverConfig::new(),
+			ResolverOpts::default(),
+			None,
+			Zones::new(),
+			Nameservers::new(),
+			Some(Arc::new(mock)),
+		)
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	fn new(
+		server: &Arc<Server>,
+		cache: Arc<Cache>,
+		breaker: Arc<Breaker>,
+		denylist: Arc<Denylist>,
+		hints: Arc<Hints>,
+		metrics: Arc<Metrics>,
+		conf: ResolverConfig,
+		opts: ResolverOpts,
+		fallback: Fallback,
+		zones: Zones,
+		nameservers: Nameservers,
+		#[cfg(any(test, feature = "mock_resolver"))] mock: Option<Arc<Mock>>,
+	) -> Arc<Self> {
+		let active = Arc::new(RwLock::new(Arc::new(Pool::new(server, &conf, &opts))));
+		let zones = Arc::new(zones);
+		Arc::new(Self {
+			fallback: fallback.clone(),
+			hooked: Arc::new(Hooked {
+				active: active.clone(),
+				fallback,
+				cache,
+				server: server.clone(),
+				#[cfg(any(test, feature = "mock_resolver"))]
+				mock: mock.clone(),
+				floor: Arc::default(),
+				audited: Arc::default(),
+				breaker,
+				denylist: denylist.clone(),
+				hints,
+				metrics: metrics.clone(),
+				zones: zones.clone(),
+			}),
+			active,
+			zones,
 			server: server.clone(),
-		}))
+			#[cfg(any(test, feature = "mock_resolver"))]
+			mock,
+			denylist,
+			metrics,
+			nameservers,
+			conf,
+			opts,
+		})
+	}
+}
+
+impl Pool {
+	fn new(server: &Server, conf: &ResolverConfig, opts: &ResolverOpts) -> Self {
+		let config = &server.config;
+		let tcp_fallback = |query: &str| {
+			config
+				.dns_tcp_fallback_by_query
+				.get(query)
+				.copied()
+				.unwrap_or(config.dns_tcp_fallback)
+		};
+
+		let mut opts = opts.clone();
+		opts.try_tcp_on_error = tcp_fallback("ip");
+		let resolver = Arc::new(TokioAsyncResolver::tokio(conf.clone(), opts.clone()));
+		let by_query = |query: &str| {
+			let try_tcp_on_error = tcp_fallback(query);
+			if try_tcp_on_error == opts.try_tcp_on_error {
+				return resolver.clone();
+			}
+
+			let mut opts = opts.clone();
+			opts.try_tcp_on_error = try_tcp_on_error;
+			Arc::new(TokioAsyncResolver::tokio(conf.clone(), opts))
+		};
+
+		let (srv, txt) = (by_query("srv"), by_query("txt"));
+		let mut strategies = BTreeMap::new();
+		let mut rules: Rules = config
+			.ip_lookup_strategy_rules
+			.iter()
+			.map(|(pattern, &strategy)| {
+				let resolver = strategies.entry(strategy).or_insert_with(|| {
+					let mut opts = opts.clone();
+					opts.ip_strategy = ip_strategy(strategy);
+					Arc::new(TokioAsyncResolver::tokio(conf.clone(), opts))
+				});
+
+				(pattern.to_ascii_lowercase(), strategy, resolver.clone())
+			})
+			.collect();
+
+		rules.sort_by_key(|(pattern, ..)| (pattern.starts_with("*."), Reverse(pattern.len())));
+
+		Self { resolver, rules, srv, txt }
+	}
+}
+
+impl Resolver {
+	/// Resolve the addresses for a name through the live resolver, bypassing
+	/// any of conduwuit's overrides. The addresses are filtered and ordered as
+	/// they are for connecting; see `usable_ips`.
+	pub async fn lookup_ips(&self, name: &str) -> Result<Vec<IpAddr>> {
+		let query: Name = name
+			.parse()
+			.map_err(|e| err!("Invalid name {name:?}: {e}"))?;
+
+		#[cfg(any(test, feature = "mock_resolver"))]
+		if let Some(mock) = self.mock.as_deref() {
+			return Ok(lookup(mock, name)?.iter().map(SocketAddr::ip).collect());
+		}
+
+		let (resolver, strategy) = self.resolver_for(name);
+		let fallback = self.fallback.as_deref();
+		let results =
+			lookup_ip(&self.server, &resolver, strategy, fallback, &self.metrics, &query)
+				.await
+				.map_err(|e| err!("Failed to resolve {name:?}: {e}"))?;
+
+		Ok(usable_ips(&self.server, &query, &results)?)
+	}
+
+	/// The resolver to query for the addresses of a name and the
+	/// `ip_lookup_strategy` it applies, as selected by `ip_resolver`.
+	pub(crate) fn resolver_for(&self, name: &str) -> (Arc<TokioAsyncResolver>, u8) {
+		ip_resolver(&self.server, &self.zones, &self.pool(), name)
+	}
+
+	/// The resolver to query for the SRV records of a name; like
+	/// `resolver_for` but following `dns_tcp_fallback_by_query`.
+	pub(crate) fn srv_resolver_for(&self, name: &str) -> Arc<TokioAsyncResolver> {
+		let name = name.trim_end_matches('.').to_ascii_lowercase();
+		zone_resolver(&self.zones, &name)
+			.cloned()
+			.unwrap_or_else(|| self.pool().srv.clone())
+	}
+
+	/// Rebuild the pool of resolvers to query only the upstream nameservers
+	/// not `excluded`, keyed as by `probe_nameservers`. Every nameserver is
+	/// kept when all would be excluded. The replaced resolvers' caches are
+	/// lost.
+	pub(super) fn exclude_nameservers(&self, excluded: &HashSet<String>) {
+		let mut conf = ResolverConfig::from_parts(
+			self.conf.domain().cloned(),
+			self.conf.search().to_vec(),
+			self.conf
+				.name_servers()
+				.iter()
+				.filter(|ns| !excluded.contains(&nameserver_key(ns)))
+				.cloned()
+				.collect::<Vec<_>>(),
+		);
+
+		if conf.name_servers().is_empty() {
+			conf = self.conf.clone();
+		}
+
+		let pool = Pool::new(&self.server, &conf, &self.opts);
+		*self.active.write().expect("locked for writing") = Arc::new(pool);
+	}
+
+	fn pool(&self) -> Arc<Pool> { self.active.read().expect("locked for reading").clone() }
+
+	/// Resolve the TXT records of a name through the live resolver, which
+	/// caches them for their TTL. The character-strings of each record are
+	/// concatenated into one string as RFC 7208 prescribes; any invalid UTF-8
+	/// is replaced.
+	pub async fn resolve_txt(&self, name: &str) -> Result<Vec<String>> {
+		#[cfg(any(test, feature = "mock_resolver"))]
+		if self.mock.is_some() {
+			return Err(err!("TXT records are not served by the mock resolver"));
+		}
+
+		self.metrics.record(QueryType::Txt);
+		let result = self.pool().txt.txt_lookup(name).await;
+		self.metrics.record_outcome(&result);
+
+		let lookup = result.map_err(|e| err!("Failed to resolve TXT of {name:?}: {e}"))?;
+		Ok(lookup
+			.iter()
+			.map(|txt| {
+				let bytes: Vec<u8> = txt
+					.txt_data()
+					.iter()
+					.flat_map(|s| s.iter().copied())
+					.collect();
+				String::from_utf8_lossy(&bytes).into_owned()
+			})
+			.collect())
+	}
+
+	/// Resolve a name through each upstream nameserver in isolation, without
+	/// caching, recording the outcome against that nameserver. The rotating
+	/// resolver does not reveal which upstream answered, so this is how a dead
+	/// or degraded nameserver is identified.
+	pub async fn probe_nameservers(
+		&self,
+		name: &str,
+	) -> Result<Vec<(String, Duration, Result<Vec<IpAddr>>)>> {
+		let query: Name = name
+			.parse()
+			.map_err(|e| err!("Invalid name {name:?}: {e}"))?;
+
+		let mut results = Vec::with_capacity(self.nameservers.len());
+		for (nameserver, resolver) in &self.nameservers {
+			let timer = Instant::now();
+			let strategy = self.server.config.ip_lookup_strategy;
+			let result = lookup_ip(&self.server, resolver, strategy, None, &self.metrics, &query)
+				.await
+				.map(|results| results.iter().collect())
+				.map_err(|e| err!("Failed to resolve {name:?}: {e}"));
+
+			self.metrics.record_probe(nameserver, result.is_ok());
+			results.push((nameserver.clone(), timer.elapsed(), result));
+		}
+
+		Ok(results)
 	}
 }
 
 impl Resolve for Resolver {
 	fn resolve(&self, name: Name) -> Resolving {
-		resolve_to_reqwest(self.server.clone(), self.resolver.clone(), name).boxed()
+		#[cfg(any(test, feature = "mock_resolver"))]
+		if let Some(mock) = self.mock.clone() {
+			return mock_to_reqwest(mock, name).boxed();
+		}
+
+		if let Err(e) = self.denylist.check(name.as_str()) {
+			return future::ready(Err(e.into())).boxed();
+		}
+
+		let (resolver, strategy) = self.resolver_for(name.as_str());
+		resolve_to_reqwest(
+			self.server.clone(),
+			resolver,
+			strategy,
+			self.fallback.clone(),
+			self.metrics.clone(),
+			name,
+		)
+		.boxed()
 	}
 }
 
 impl Resolve for Hooked {
 	fn resolve(&self, name: Name) -> Resolving {
-		hooked_resolve(self.cache.clone(), self.server.clone(), self.resolver.clone(), name)
-			.boxed()
+		#[cfg(any(test, feature = "mock_resolver"))]
+		if let Some(mock) = self.mock.clone() {
+			return mock_to_reqwest(mock, name).boxed();
+		}
+
+		if let Some(addr) = ip_literal(name.as_str()) {
+			let addrs: Addrs = Box::new(
+				std::iter::once(addr)
+					.filter(|addr| !(self.server.config.dns_disable_ipv6 && addr.is_ipv6())),
+			);
+
+			return future::ready(Ok(addrs)).boxed();
+		}
+
+		if let Err(e) = self.denylist.check(name.as_str()) {
+			return future::ready(Err(e.into())).boxed();
+		}
+
+		if let Err(e) = self.breaker.check(&self.server, name.as_str()) {
+			return future::ready(Err(e.into())).boxed();
+		}
+
+		let key = name.as_str().to_owned();
+		let breaker = self.breaker.clone();
+		let hints = self.hints.clone();
+		let server = self.server.clone();
+		let (resolver, strategy) = ip_resolver(
+			&self.server,
+			&self.zones,
+			&self.active.read().expect("locked for reading"),
+			name.as_str(),
+		);
+
+		hooked_resolve(
+			self.cache.clone(),
+			self.floor.clone(),
+			self.audited.clone(),
+			self.server.clone(),
+			resolver,
+			strategy,
+			self.fallback.clone(),
+			self.metrics.clone(),
+			name,
+		)
+		.map(move |result| {
+			breaker.record(&server, &key, result.is_ok());
+			result.map(|addrs| hints.reorder(&server, &key, addrs))
+		})
+		.boxed()
 	}
 }
 
@@ -98,52 +578,421 @@ fn resolve(&self, name: Name) -> Resolving {
 	skip_all,
 	fields(name = ?name.as_str())
 )]
+#[allow(clippy::too_many_arguments)]
 async fn hooked_resolve(
 	cache: Arc<Cache>,
+	floor: Arc<Floor>,
+	audited: Arc<Audited>,
 	server: Arc<Server>,
 	resolver: Arc<TokioAsyncResolver>,
+	strategy: u8,
+	fallback: Fallback,
+	metrics: Arc<Metrics>,
 	name: Name,
 ) -> Result<Addrs, Box<dyn std::error::Error + Send + Sync>> {
 	match cache.get_override(name.as_str()).await {
-		| Ok(cached) if cached.valid() => cached_to_reqwest(cached).await,
-		| Ok(CachedOverride { overriding, .. }) if overriding.is_some() =>
+		| Ok(cached) if cached.valid() => {
+			audit_override(&audited, name.as_str(), &cached);
+			cached_to_reqwest(&server, cached).await
+		},
+		| Ok(cached @ CachedOverride { overriding: Some(_), .. }) => {
+			audit_override(&audited, name.as_str(), &cached);
+			let overriding = cached
+				.overriding
+				.expect("overriding is set for this record");
+
 			resolve_to_reqwest(
 				server,
 				resolver,
+				strategy,
+				fallback,
+				metrics,
 				overriding
-					.as_deref()
-					.map(str::parse)
-					.expect("overriding is set for this record")
+					.parse()
 					.expect("overriding is a valid internet name"),
 			)
 			.boxed()
-			.await,
+			.await
+		},
+
+		| _ =>
+			resolve_floored(floor, server, resolver, strategy, fallback, metrics, name)
+				.boxed()
+				.await,
+	}
+}
+
+/// Logs that resolution of `name` was served from an override rather than
+/// live DNS, at most once per name every `AUDIT_INTERVAL`. Overrides pinned by
+/// an administrator are logged at info level; those cached from resolving a
+/// destination's delegation, which are routine, at debug level.
+fn audit_override(audited: &Audited, name: &str, cached: &CachedOverride) {
+	let now = Instant::now();
+	let mut audited = audited.write().expect("locked for writing");
+	if audited
+		.get(name)
+		.is_some_and(|logged| now.saturating_duration_since(*logged) < AUDIT_INTERVAL)
+	{
+		return;
+	}
+
+	audited.retain(|_, logged| now.saturating_duration_since(*logged) < AUDIT_INTERVAL);
+	audited.insert(name.to_owned(), now);
+	drop(audited);
 
-		| _ => resolve_to_reqwest(server, resolver, name).boxed().await,
+	let CachedOverride { ips, port, overriding, expires_at, .. } = cached;
+	let overriding = overriding.as_deref();
+	if expires_at.is_some() {
+		info!(name, ?ips, port, ?overriding, source = "pinned", "Resolved from override");
+	} else {
+		debug!(name, ?ips, port, ?overriding, source = "delegation", "Resolved from override");
 	}
 }
 
+/// Resolves through hickory while retaining the result for at least
+/// `dns_ttl_floor` seconds, even when the record's own TTL is shorter.
+async fn resolve_floored(
+	floor: Arc<Floor>,
+	server: Arc<Server>,
+	resolver: Arc<TokioAsyncResolver>,
+	strategy: u8,
+	fallback: Fallback,
+	metrics: Arc<Metrics>,
+	name: Name,
+) -> ResolvingResult {
+	let min_ttl = Duration::from_secs(server.config.dns_ttl_floor);
+	if min_ttl.is_zero() {
+		return resolve_to_reqwest(server, resolver, strategy, fallback, metrics, name).await;
+	}
+
+	let now = Instant::now();
+	let key = (name.as_str().to_owned(), strategy);
+	let retained = floor
+		.read()
+		.expect("locked for reading")
+		.get(&key)
+		.filter(|(expire, _)| *expire > now)
+		.map(|(_, ips)| ips.clone());
+
+	if let Some(ips) = retained {
+		return Ok(Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0))));
+	}
+
+	let results =
+		lookup_ip(&server, &resolver, strategy, fallback.as_deref(), &metrics, &name).await?;
+	let expire = results
+		.valid_until()
+		.max(now.checked_add(min_ttl).unwrap_or(now));
+	let ips = usable_ips(&server, &name, &results)?;
+
+	let mut floor = floor.write().expect("locked for writing");
+	floor.retain(|_, (expire, _)| *expire > now);
+	floor.insert(key, (expire, ips.clone()));
+
+	Ok(Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0))))
+}
+
 async fn resolve_to_reqwest(
 	server: Arc<Server>,
 	resolver: Arc<TokioAsyncResolver>,
+	strategy: u8,
+	fallback: Fallback,
+	metrics: Arc<Metrics>,
 	name: Name,
 ) -> ResolvingResult {
-	use std::{io, io::ErrorKind::Interrupted};
+	let results =
+		lookup_ip(&server, &resolver, strategy, fallback.as_deref(), &metrics, &name).await?;
+	let ips = usable_ips(&server, &name, &results)?;
+
+	Ok(Box::new(ips.into_iter().map(|ip| SocketAddr::new(ip, 0))))
+}
+
+/// The addresses of a successful lookup ordered for connecting, failing when
+/// there are none usable rather than handing the connector an empty list. The
+/// error names the record types the answer held instead, such as only a CNAME
+/// to a name without addresses, to tell misconfigured remote DNS apart from a
+/// failed query. Fewer distinct addresses than `dns_min_addresses` also fail.
+fn usable_ips(server: &Server, name: &Name, results: &LookupIp) -> io::Result<Vec<IpAddr>> {
+	let ips = order_families(server, results.iter());
+	if ips.is_empty() {
+		return Err(no_usable_ips(name, results));
+	}
+
+	let min = min_addresses(server, name.as_str());
+	let distinct = ips.iter().collect::<BTreeSet<_>>().len();
+	if distinct < min {
+		return Err(io::Error::new(
+			io::ErrorKind::NotFound,
+			format!(
+				"{:?} resolved to {distinct} usable addresses; at least {min} are required",
+				name.as_str()
+			),
+		));
+	}
+
+	Ok(ips)
+}
+
+/// Minimum number of distinct addresses `name` must resolve to, from
+/// `dns_min_addresses_overrides` or else `dns_min_addresses`. The name is
+/// matched regardless of case or a trailing root dot.
+fn min_addresses(server: &Server, name: &str) -> usize {
+	server
+		.config
+		.dns_min_addresses_overrides
+		.get(&normalize_host(name))
+		.copied()
+		.unwrap_or(server.config.dns_min_addresses)
+}
+
+fn no_usable_ips(name: &Name, results: &LookupIp) -> io::Error {
+	let types: BTreeSet<String> = results
+		.as_lookup()
+		.record_iter()
+		.map(|record| record.record_type().to_string())
+		.collect();
+
+	let held = if types.is_empty() {
+		"no records".to_owned()
+	} else {
+		types.into_iter().collect::<Vec<_>>().join(", ")
+	};
+
+	io::Error::new(
+		io::ErrorKind::NotFound,
+		format!("{:?} resolved to no usable addresses; the answer held {held}", name.as_str()),
+	)
+}
+
+/// Orders resolved addresses for connecting when `dns_happy_eyeballs` is
+/// enabled, otherwise by `dns_prefer_family`. IPv6 addresses are dropped when
+/// `dns_disable_ipv6` is enabled, and each family is capped at
+/// `dns_max_addresses_per_family`.
+pub(super) fn order_families<I>(server: &Server, ips: I) -> Vec<IpAddr>
+where
+	I: Iterator<Item = IpAddr>,
+{
+	let disable_ipv6 = server.config.dns_disable_ipv6;
+	let ips = ips.filter(|ip| !(disable_ipv6 && ip.is_ipv6()));
+	let ips = cap_per_family(ips, server.config.dns_max_addresses_per_family);
+	if server.config.dns_happy_eyeballs {
+		return interleave_families(ips);
+	}
+
+	let mut ips: Vec<_> = ips.collect();
+	prefer_family(&mut ips, &server.config.dns_prefer_family);
+	ips
+}
+
+/// Keeps up to `max` addresses of each family in the order given; 0 keeps
+/// every address.
+pub(super) fn cap_per_family<I>(ips: I, max: usize) -> impl Iterator<Item = IpAddr>
+where
+	I: Iterator<Item = IpAddr>,
+{
+	let (mut v4, mut v6) = (0_usize, 0_usize);
+	ips.filter(move |ip| {
+		let count = if ip.is_ipv6() { &mut v6 } else { &mut v4 };
+		*count = count.saturating_add(1);
+		max == 0 || *count <= max
+	})
+}
+
+/// Stably sorts addresses of the preferred family, "v4" or "v6", ahead of the
+/// other; any other preference keeps the order unchanged.
+pub(super) fn prefer_family(ips: &mut [IpAddr], family: &str) {
+	match family {
+		| "v4" => ips.sort_by_key(IpAddr::is_ipv6),
+		| "v6" => ips.sort_by_key(IpAddr::is_ipv4),
+		| _ => {},
+	}
+}
+
+/// Interleaves IPv6 and IPv4 addresses starting with IPv6, as recommended by
+/// RFC 8305. The connector attempts the first address's family and races the
+/// other family shortly after, so leading with IPv6 while keeping an IPv4
+/// address near the front gives fast failover when either family is broken.
+pub(super) fn interleave_families<I>(ips: I) -> Vec<IpAddr>
+where
+	I: Iterator<Item = IpAddr>,
+{
+	let (v6, v4): (Vec<_>, Vec<_>) = ips.partition(IpAddr::is_ipv6);
+	let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+
+	let mut ips = Vec::with_capacity(v6.len().saturating_add(v4.len()));
+	loop {
+		match (v6.next(), v4.next()) {
+			| (None, None) => break ips,
+			| (v6, v4) => ips.extend(v6.into_iter().chain(v4)),
+		}
+	}
+}
+
+/// Resolves through hickory, retrying timeouts for names listed in
+/// `dns_timeout_overrides`, then through the fallback resolver if there is one
+/// and the primary failed. Queries are counted by the `strategy` the resolver
+/// applies; the fallback applies the global `ip_lookup_strategy`.
+async fn lookup_ip(
+	server: &Server,
+	resolver: &TokioAsyncResolver,
+	strategy: u8,
+	fallback: Option<&TokioAsyncResolver>,
+	metrics: &Metrics,
+	name: &Name,
+) -> Result<LookupIp, Box<dyn std::error::Error + Send + Sync>> {
+	use std::io::ErrorKind::Interrupted;
 
 	let handle_shutdown = || Box::new(io::Error::new(Interrupted, "Server shutting down"));
-	let handle_results =
-		|results: LookupIp| Box::new(results.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+
+	let query = || {
+		metrics.record_ip(strategy);
+		resolver.lookup_ip(name.as_str())
+	};
+
+	let budget = server
+		.config
+		.dns_timeout_overrides
+		.get(name.as_str())
+		.copied()
+		.map(Duration::from_secs);
+
+	let primary = async {
+		let Some(budget) = budget else {
+			return query().await;
+		};
+
+		let retry = async {
+			loop {
+				let results = query().await;
+				if !results
+					.as_ref()
+					.is_err_and(|e| matches!(e.kind(), ResolveErrorKind::Timeout))
+				{
+					break results;
+				}
+			}
+		};
+
+		tokio::time::timeout(budget, retry)
+			.await
+			.unwrap_or_else(|_| Err(ResolveErrorKind::Timeout.into()))
+	};
+
+	let lookup = async {
+		let timer = Instant::now();
+		let results = match (primary.await, fallback) {
+			| (Err(e), Some(fallback)) => {
+				debug_warn!(name = ?name.as_str(), "Resolution failed, trying fallback: {e}");
+				metrics.record_ip(server.config.ip_lookup_strategy);
+				fallback.lookup_ip(name.as_str()).await
+			},
+			| (results, _) => results,
+		};
+
+		metrics.record_outcome(&results);
+		metrics.record_slow(
+			name.as_str(),
+			timer.elapsed(),
+			Duration::from_millis(server.config.dns_slow_lookup_threshold_ms),
+		);
+
+		results
+	};
 
 	tokio::select! {
-		results = resolver.lookup_ip(name.as_str()) => Ok(handle_results(results?)),
+		results = lookup => Ok(results?),
 		() = server.until_shutdown() => Err(handle_shutdown()),
 	}
 }
 
-async fn cached_to_reqwest(cached: CachedOverride) -> ResolvingResult {
+fn ip_strategy(strategy: u8) -> LookupIpStrategy {
+	match strategy {
+		| 1 => LookupIpStrategy::Ipv4Only,
+		| 2 => LookupIpStrategy::Ipv6Only,
+		| 3 => LookupIpStrategy::Ipv4AndIpv6,
+		| 4 => LookupIpStrategy::Ipv6thenIpv4,
+		| _ => LookupIpStrategy::Ipv4thenIpv6,
+	}
+}
+
+/// Parses a name which is an IP literal rather than a hostname: a bare IPv4
+/// or IPv6 address, or either with a port (IPv6 bracketed). Addresses without
+/// a port are returned with port 0, leaving the port to the caller.
+pub(super) fn ip_literal(name: &str) -> Option<SocketAddr> {
+	name.parse().ok().or_else(|| {
+		let ip = name
+			.strip_prefix('[')
+			.and_then(|name| name.strip_suffix(']'))
+			.unwrap_or(name);
+
+		ip.parse().ok().map(|ip| SocketAddr::new(ip, 0))
+	})
+}
+
+/// Whether a lowercase `name` matches an `ip_lookup_strategy_rules` pattern:
+/// either the name itself, or `*.` followed by a domain to match any of its
+/// subdomains.
+pub(super) fn rule_matches(pattern: &str, name: &str) -> bool {
+	match pattern.strip_prefix("*.") {
+		| None => name == pattern,
+		| Some(domain) => name
+			.strip_suffix(domain)
+			.and_then(|sub| sub.strip_suffix('.'))
+			.is_some_and(|sub| !sub.is_empty()),
+	}
+}
+
+/// The resolver for an address lookup of `name` and the `ip_lookup_strategy`
+/// it applies: that of the most specific zone of `dns_forward_zones`
+/// containing the name, otherwise that of the most specific matching
+/// `ip_lookup_strategy_rules` pattern, otherwise the default.
+fn ip_resolver(
+	server: &Server,
+	zones: &Zones,
+	pool: &Pool,
+	name: &str,
+) -> (Arc<TokioAsyncResolver>, u8) {
+	let name = name.trim_end_matches('.').to_ascii_lowercase();
+	let default = server.config.ip_lookup_strategy;
+	if let Some(resolver) = zone_resolver(zones, &name) {
+		return (resolver.clone(), default);
+	}
+
+	pool.rules
+		.iter()
+		.find(|(pattern, ..)| rule_matches(pattern, &name))
+		.map_or_else(
+			|| (pool.resolver.clone(), default),
+			|(_, strategy, resolver)| (resolver.clone(), *strategy),
+		)
+}
+
+/// The forwarding resolver of the most specific zone containing `name`, which
+/// must be lowercase and without a trailing dot.
+fn zone_resolver<'a>(zones: &'a Zones, name: &str) -> Option<&'a Arc<TokioAsyncResolver>> {
+	zones
+		.iter()
+		.find(|(zone, _)| zone_matches(zone, name))
+		.map(|(_, resolver)| resolver)
+}
+
+/// Whether `name` is `zone` or one of its subdomains.
+pub(super) fn zone_matches(zone: &str, name: &str) -> bool {
+	name.strip_suffix(zone)
+		.is_some_and(|sub| sub.is_empty() || sub.ends_with('.'))
+}
+
+/// Identifies an upstream nameserver by its address and protocol.
+fn nameserver_key(ns: &NameServerConfig) -> String {
+	format!("{}/{}", ns.socket_addr, ns.protocol)
+}
+
+async fn cached_to_reqwest(server: &Server, cached: CachedOverride) -> ResolvingResult {
+	let disable_ipv6 = server.config.dns_disable_ipv6;
 	let addrs = cached
 		.ips
 		.into_iter()
+		.filter(move |ip| !(disable_ipv6 && ip.is_ipv6()))
 		.map(move |ip| SocketAddr::new(ip, cached.port));
 
 	Ok(Box::new(addrs))
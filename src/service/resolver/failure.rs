@@ -0,0 +1,65 @@
+use std::{error::Error, iter};
+
+use hickory_resolver::{
+	error::{ResolveError, ResolveErrorKind},
+	proto::op::ResponseCode,
+};
+
+/// How resolving a name failed, telling names which do not resolve apart from
+/// transient failures worth retrying soon.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResolveFailure {
+	/// NXDOMAIN: the name does not exist.
+	NxDomain,
+
+	/// The name exists but has no records of the type queried.
+	NoRecords,
+
+	/// SERVFAIL, or any other error response code from the nameserver.
+	ServFail,
+
+	/// No nameserver answered in time.
+	Timeout,
+
+	/// No nameserver could be reached.
+	NoConnections,
+
+	/// Any other failure, such as a malformed response.
+	Other,
+}
+
+impl ResolveFailure {
+	#[must_use]
+	pub fn classify(e: &ResolveError) -> Self {
+		match e.kind() {
+			| ResolveErrorKind::NoRecordsFound { response_code, .. } => match *response_code {
+				| ResponseCode::NXDomain => Self::NxDomain,
+				| ResponseCode::NoError => Self::NoRecords,
+				| _ => Self::ServFail,
+			},
+			| ResolveErrorKind::Timeout => Self::Timeout,
+			| ResolveErrorKind::NoConnections => Self::NoConnections,
+			| _ => Self::Other,
+		}
+	}
+
+	/// The failure of the first resolver error found walking the sources of
+	/// `e`, such as that behind a request failing to connect.
+	#[must_use]
+	pub fn find(e: &(dyn Error + 'static)) -> Option<Self> {
+		iter::successors(Some(e), |e| e.source())
+			.find_map(|e| e.downcast_ref::<ResolveError>())
+			.map(Self::classify)
+	}
+
+	/// Whether the nameserver answered authoritatively that the name has no
+	/// addresses, so retrying soon is pointless; other failures are likely
+	/// transient.
+	#[must_use]
+	pub fn is_permanent(self) -> bool { matches!(self, Self::NxDomain | Self::NoRecords) }
+
+	/// Whether no nameserver could be reached or answered at all, as opposed to
+	/// one answering with an error about the name queried.
+	#[must_use]
+	pub fn is_unreachable(self) -> bool { matches!(self, Self::Timeout | Self::NoConnections) }
+}
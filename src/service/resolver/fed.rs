@@ -41,6 +41,14 @@ pub(crate) fn add_port_to_hostname(dest: &str) -> FedDest {
 	)
 }
 
+/// A hostname without a port of its own, to be connected to on `port`.
+pub(crate) fn hostname_with_port(host: &str, port: u16) -> FedDest {
+	FedDest::Named(
+		host.to_owned(),
+		PortString::from(format!(":{port}").as_str()).unwrap_or_else(|_| FedDest::default_port()),
+	)
+}
+
 impl FedDest {
 	pub(crate) fn https_string(&self) -> String {
 		match self {
@@ -73,6 +81,24 @@ pub(crate) fn port(&self) -> Option<u16> {
 		}
 	}
 
+	/// Socket addresses for this destination given the addresses its hostname
+	/// resolved to; a literal destination is returned as-is.
+	#[must_use]
+	pub(crate) fn socket_addrs<I>(&self, ips: I) -> Vec<SocketAddr>
+	where
+		I: IntoIterator<Item = IpAddr>,
+	{
+		match self {
+			| Self::Literal(addr) => vec![*addr],
+			| Self::Named(..) => {
+				let port = self.port().unwrap_or(8448);
+				ips.into_iter()
+					.map(|ip| SocketAddr::new(ip, port))
+					.collect()
+			},
+		}
+	}
+
 	#[inline]
 	#[must_use]
 	pub fn default_port() -> PortString {
@@ -0,0 +1,97 @@
+use std::{
+	collections::HashMap,
+	net::{IpAddr, SocketAddr},
+	sync::RwLock,
+	time::SystemTime,
+};
+
+use conduwuit::{implement, Server};
+use reqwest::dns::Addrs;
+
+use super::normalize_host;
+
+/// The address each host was last successfully connected to, as reported by
+/// the federation sender; moved to the front of the host's resolved addresses
+/// when `dns_prefer_last_good_address` is enabled. Each is kept with when it
+/// was recorded, so stale hints can be told apart.
+#[derive(Default)]
+pub struct Hints {
+	addrs: RwLock<HashMap<String, (IpAddr, SystemTime)>>,
+}
+
+/// Record a successful connection to `host` at `ip`.
+#[implement(Hints)]
+pub fn record(&self, server: &Server, host: &str, ip: IpAddr) {
+	if !server.config.dns_prefer_last_good_address {
+		return;
+	}
+
+	self.addrs
+		.write()
+		.expect("locked for writing")
+		.insert(normalize_host(host), (ip, SystemTime::now()));
+}
+
+/// Move the last good address of `host` to the front of `addrs`, keeping the
+/// order of the rest.
+#[implement(Hints)]
+pub(super) fn reorder(&self, server: &Server, host: &str, addrs: Addrs) -> Addrs {
+	if !server.config.dns_prefer_last_good_address {
+		return addrs;
+	}
+
+	let Some(hint) = self.get(host) else {
+		return addrs;
+	};
+
+	let mut addrs: Vec<_> = addrs.collect();
+	prefer_addr(&mut addrs, hint);
+	Box::new(addrs.into_iter())
+}
+
+/// The last good address of `host`, if any.
+#[implement(Hints)]
+#[must_use]
+pub fn get(&self, host: &str) -> Option<IpAddr> {
+	self.addrs
+		.read()
+		.expect("locked for reading")
+		.get(&normalize_host(host))
+		.map(|&(ip, _)| ip)
+}
+
+/// Every host's last good address with when it was recorded, sorted by host.
+#[implement(Hints)]
+#[must_use]
+pub fn hints(&self) -> Vec<(String, IpAddr, SystemTime)> {
+	let mut hints: Vec<_> = self
+		.addrs
+		.read()
+		.expect("locked for reading")
+		.iter()
+		.map(|(host, &(ip, recorded))| (host.clone(), ip, recorded))
+		.collect();
+
+	hints.sort_unstable();
+	hints
+}
+
+/// Forget the last good address of `host`; returns false if it had none.
+#[implement(Hints)]
+pub fn remove(&self, host: &str) -> bool {
+	self.addrs
+		.write()
+		.expect("locked for writing")
+		.remove(&normalize_host(host))
+		.is_some()
+}
+
+#[implement(Hints)]
+pub fn clear(&self) { self.addrs.write().expect("locked for writing").clear(); }
+
+/// Moves the first address with the hinted IP to the front.
+pub(super) fn prefer_addr(addrs: &mut [SocketAddr], hint: IpAddr) {
+	if let Some(pos) = addrs.iter().position(|addr| addr.ip() == hint) {
+		addrs[..=pos].rotate_right(1);
+	}
+}
@@ -0,0 +1,187 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		RwLock,
+	},
+	time::{Duration, Instant},
+};
+
+use conduwuit::{implement, warn};
+use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+
+/// Counters of queries issued to the resolver, labeled by record type.
+#[derive(Default)]
+pub struct Metrics {
+	a: AtomicU64,
+	aaaa: AtomicU64,
+	srv: AtomicU64,
+	txt: AtomicU64,
+	nameservers: RwLock<BTreeMap<String, Probes>>,
+	unreachable: RwLock<Unreachable>,
+	slow: RwLock<HashMap<String, Instant>>,
+}
+
+/// Consecutive queries which no nameserver answered, and when the first of
+/// them was made.
+#[derive(Default)]
+struct Unreachable {
+	count: u64,
+	since: Option<Instant>,
+}
+
+/// Consecutive unanswered queries after which DNS is considered down.
+const OUTAGE_QUERIES: u64 = 5;
+
+/// Interval within which a slow lookup of the same name is not logged again.
+const SLOW_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Duration over which queries must have gone unanswered before DNS is
+/// considered down.
+const OUTAGE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Outcomes of probing an upstream nameserver individually.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Probes {
+	pub succeeded: u64,
+	pub failed: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QueryType {
+	A,
+	Aaaa,
+	Srv,
+	Txt,
+}
+
+impl QueryType {
+	pub const ALL: [Self; 4] = [Self::A, Self::Aaaa, Self::Srv, Self::Txt];
+}
+
+impl fmt::Display for QueryType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			| Self::A => "A",
+			| Self::Aaaa => "AAAA",
+			| Self::Srv => "SRV",
+			| Self::Txt => "TXT",
+		})
+	}
+}
+
+/// Count a query of the given record type.
+#[implement(Metrics)]
+pub fn record(&self, query: QueryType) { self.counter(query).fetch_add(1, Ordering::Relaxed); }
+
+/// Count an address lookup according to the configured `ip_lookup_strategy`.
+/// Strategies querying one family before falling back to the other are
+/// counted against the family queried first.
+#[implement(Metrics)]
+pub fn record_ip(&self, ip_lookup_strategy: u8) {
+	match ip_lookup_strategy {
+		| 1 => self.record(QueryType::A),
+		| 2 | 4 => self.record(QueryType::Aaaa),
+		| 3 => {
+			self.record(QueryType::A);
+			self.record(QueryType::Aaaa);
+		},
+		| _ => self.record(QueryType::A),
+	}
+}
+
+/// Number of queries of each record type since startup.
+#[implement(Metrics)]
+#[must_use]
+pub fn counts(&self) -> Vec<(QueryType, u64)> {
+	QueryType::ALL
+		.into_iter()
+		.map(|query| (query, self.counter(query).load(Ordering::Relaxed)))
+		.collect()
+}
+
+/// Track whether any nameserver answered a query. Negative answers count as
+/// answered; only timeouts and a lack of connections count against.
+#[implement(Metrics)]
+pub fn record_outcome<T>(&self, result: &Result<T, ResolveError>) {
+	let answered = !result.as_ref().is_err_and(|e| {
+		matches!(e.kind(), ResolveErrorKind::Timeout | ResolveErrorKind::NoConnections)
+	});
+
+	let mut unreachable = self.unreachable.write().expect("locked for writing");
+	if answered {
+		*unreachable = Unreachable::default();
+	} else {
+		unreachable.count = unreachable.count.saturating_add(1);
+		unreachable.since.get_or_insert_with(Instant::now);
+	}
+}
+
+/// How long DNS has been down, when every query for some time now has gone
+/// unanswered by all nameservers.
+#[implement(Metrics)]
+#[must_use]
+pub fn outage(&self) -> Option<Duration> {
+	let unreachable = self.unreachable.read().expect("locked for reading");
+	unreachable
+		.since
+		.map(|since| since.elapsed())
+		.filter(|elapsed| unreachable.count >= OUTAGE_QUERIES && *elapsed >= OUTAGE_WINDOW)
+}
+
+/// Warn of an address lookup of `name` which took `elapsed`, beyond the
+/// `dns_slow_lookup_threshold_ms`, at most once per name every
+/// `SLOW_LOG_INTERVAL`.
+#[implement(Metrics)]
+pub fn record_slow(&self, name: &str, elapsed: Duration, threshold: Duration) {
+	if threshold.is_zero() || elapsed <= threshold {
+		return;
+	}
+
+	let now = Instant::now();
+	let mut slow = self.slow.write().expect("locked for writing");
+	if slow
+		.get(name)
+		.is_some_and(|logged| now.saturating_duration_since(*logged) < SLOW_LOG_INTERVAL)
+	{
+		return;
+	}
+
+	slow.retain(|_, logged| now.saturating_duration_since(*logged) < SLOW_LOG_INTERVAL);
+	slow.insert(name.to_owned(), now);
+	drop(slow);
+
+	warn!(name, ?elapsed, ?threshold, "Slow DNS lookup");
+}
+
+/// Count the outcome of probing an upstream nameserver.
+#[implement(Metrics)]
+pub fn record_probe(&self, nameserver: &str, ok: bool) {
+	let mut nameservers = self.nameservers.write().expect("locked for writing");
+	let probes = nameservers.entry(nameserver.to_owned()).or_default();
+	let count = if ok { &mut probes.succeeded } else { &mut probes.failed };
+	*count = count.saturating_add(1);
+}
+
+/// Probe outcomes of each upstream nameserver since startup.
+#[implement(Metrics)]
+#[must_use]
+pub fn probes(&self) -> Vec<(String, Probes)> {
+	self.nameservers
+		.read()
+		.expect("locked for reading")
+		.iter()
+		.map(|(nameserver, probes)| (nameserver.clone(), *probes))
+		.collect()
+}
+
+#[implement(Metrics)]
+fn counter(&self, query: QueryType) -> &AtomicU64 {
+	match query {
+		| QueryType::A => &self.a,
+		| QueryType::Aaaa => &self.aaaa,
+		| QueryType::Srv => &self.srv,
+		| QueryType::Txt => &self.txt,
+	}
+}
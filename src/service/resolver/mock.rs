@@ -0,0 +1,21 @@
+use std::{collections::HashMap, io, net::SocketAddr, sync::Arc};
+
+use reqwest::dns::{Addrs, Name};
+
+/// Static table of addresses served by a mock resolver in place of DNS.
+pub type Mock = HashMap<String, Vec<SocketAddr>>;
+
+pub(super) async fn mock_to_reqwest(
+	mock: Arc<Mock>,
+	name: Name,
+) -> Result<Addrs, Box<dyn std::error::Error + Send + Sync>> {
+	let addrs = lookup(&mock, name.as_str())?;
+
+	Ok(Box::new(addrs.into_iter()))
+}
+
+pub(super) fn lookup(mock: &Mock, name: &str) -> io::Result<Vec<SocketAddr>> {
+	mock.get(name).cloned().ok_or_else(|| {
+		io::Error::new(io::ErrorKind::NotFound, format!("{name:?} not found in mock resolver"))
+	})
+}
@@ -1,46 +1,129 @@
 pub mod actual;
+pub mod breaker;
 pub mod cache;
+pub mod denylist;
 mod dns;
+pub mod failure;
 pub mod fed;
+pub mod hints;
+pub mod metrics;
+#[cfg(any(test, feature = "mock_resolver"))]
+pub mod mock;
+pub mod quarantine;
+pub mod selftest;
 mod tests;
+mod trace;
+pub mod warm;
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use arrayvec::ArrayString;
-use conduwuit::{utils::MutexMap, Result, Server};
+use async_trait::async_trait;
+use conduwuit::{utils::MutexMap, warn, Result, Server};
+use tokio::{
+	sync::Notify,
+	time::{interval, MissedTickBehavior},
+};
 
-use self::{cache::Cache, dns::Resolver};
-use crate::{client, Dep};
+use self::{
+	breaker::Breaker, cache::Cache, denylist::Denylist, dns::Resolver, hints::Hints,
+	metrics::Metrics, quarantine::Quarantine,
+};
+use crate::{client, rooms, Dep};
 
 pub struct Service {
 	pub cache: Arc<Cache>,
+	pub breaker: Arc<Breaker>,
+	pub denylist: Arc<Denylist>,
+	pub hints: Arc<Hints>,
+	pub metrics: Arc<Metrics>,
+	pub quarantine: Quarantine,
 	pub resolver: Arc<Resolver>,
 	resolving: Resolving,
+	revalidating: Resolving,
+	interrupt: Notify,
 	services: Services,
 }
 
 struct Services {
 	server: Arc<Server>,
 	client: Dep<client::Service>,
+	state_cache: Dep<rooms::state_cache::Service>,
 }
 
 type Resolving = MutexMap<NameBuf, ()>;
 type NameBuf = ArrayString<256>;
 
+#[async_trait]
 impl crate::Service for Service {
 	#[allow(clippy::as_conversions, clippy::cast_sign_loss, clippy::cast_possible_truncation)]
 	fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
 		let cache = Cache::new(&args);
+		let breaker = Arc::new(Breaker::default());
+		let denylist = Arc::new(Denylist::new(args.server));
+		let hints = Arc::new(Hints::default());
+		let metrics = Arc::new(Metrics::default());
 		Ok(Arc::new(Self {
 			cache: cache.clone(),
-			resolver: Resolver::build(args.server, cache)?,
+			breaker: breaker.clone(),
+			denylist: denylist.clone(),
+			hints: hints.clone(),
+			metrics: metrics.clone(),
+			quarantine: Quarantine::default(),
+			resolver: Resolver::build(args.server, cache, breaker, denylist, hints, metrics)?,
 			resolving: MutexMap::new(),
+			revalidating: MutexMap::new(),
+			interrupt: Notify::new(),
 			services: Services {
 				server: args.server.clone(),
 				client: args.depend::<client::Service>("client"),
+				state_cache: args.depend::<rooms::state_cache::Service>("rooms::state_cache"),
 			},
 		}))
 	}
 
+	async fn worker(self: Arc<Self>) -> Result<()> {
+		let config = &self.services.server.config;
+		if config.dns_selftest_on_startup {
+			self.self_test_logged().await;
+		}
+
+		if config.dns_warm_on_startup > 0 {
+			self.warm_most_federated(config.dns_warm_on_startup).await;
+		}
+
+		if config.dns_quarantine_interval == 0 {
+			return Ok(());
+		}
+
+		let period = Duration::from_secs(config.dns_quarantine_interval);
+		let mut i = interval(period);
+		i.set_missed_tick_behavior(MissedTickBehavior::Delay);
+		loop {
+			tokio::select! {
+				() = self.interrupt.notified() => break,
+				_ = i.tick() => (),
+			}
+
+			if let Err(e) = self.probe_quarantine().await {
+				warn!("Failed to probe nameservers: {e}");
+			}
+		}
+
+		Ok(())
+	}
+
+	fn interrupt(&self) { self.interrupt.notify_waiters(); }
+
+	fn clear_cache(&self) {
+		self.breaker.clear();
+		self.hints.clear();
+	}
+
 	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
 }
+
+/// Hostnames compare case-insensitively and without any trailing root dot.
+pub(super) fn normalize_host(host: &str) -> String {
+	host.trim_end_matches('.').to_ascii_lowercase()
+}
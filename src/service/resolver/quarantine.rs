@@ -0,0 +1,105 @@
+use std::{
+	collections::{BTreeMap, HashSet},
+	sync::RwLock,
+	time::{Duration, SystemTime},
+};
+
+use conduwuit::{implement, info, warn, Result};
+
+/// Health of each upstream nameserver as observed by periodic probes. Those
+/// failing `dns_quarantine_threshold` consecutive probes are quarantined,
+/// i.e. removed from the rotation, until a probe succeeds again.
+#[derive(Default)]
+pub struct Quarantine {
+	states: RwLock<BTreeMap<String, Health>>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Health {
+	pub consecutive_failures: u32,
+
+	/// Latency of the most recent successful probe.
+	pub latency: Option<Duration>,
+
+	/// When the nameserver was removed from the rotation, if it is.
+	pub quarantined_since: Option<SystemTime>,
+}
+
+/// Record the outcome of probing `nameserver`, the latency if it succeeded.
+/// Returns whether it entered or left quarantine.
+#[implement(Quarantine)]
+pub(super) fn record(&self, threshold: u32, nameserver: &str, latency: Option<Duration>) -> bool {
+	let mut states = self.states.write().expect("locked for writing");
+	let health = states.entry(nameserver.to_owned()).or_default();
+	let was_quarantined = health.quarantined_since.is_some();
+	match latency {
+		| Some(latency) => {
+			health.consecutive_failures = 0;
+			health.latency = Some(latency);
+			health.quarantined_since = None;
+			if was_quarantined {
+				info!(%nameserver, "Nameserver recovered; returning it to the rotation");
+			}
+		},
+		| None => {
+			health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+			if !was_quarantined && health.consecutive_failures >= threshold {
+				warn!(%nameserver, "Nameserver failed {threshold} probes; quarantining");
+				health.quarantined_since = Some(SystemTime::now());
+			}
+		},
+	}
+
+	was_quarantined != health.quarantined_since.is_some()
+}
+
+/// Nameservers currently removed from the rotation.
+#[implement(Quarantine)]
+#[must_use]
+pub fn quarantined(&self) -> HashSet<String> {
+	self.states
+		.read()
+		.expect("locked for reading")
+		.iter()
+		.filter(|(_, health)| health.quarantined_since.is_some())
+		.map(|(nameserver, _)| nameserver.clone())
+		.collect()
+}
+
+/// Health of each nameserver probed since startup.
+#[implement(Quarantine)]
+#[must_use]
+pub fn states(&self) -> Vec<(String, Health)> {
+	self.states
+		.read()
+		.expect("locked for reading")
+		.iter()
+		.map(|(nameserver, health)| (nameserver.clone(), *health))
+		.collect()
+}
+
+impl super::Service {
+	/// Probe each upstream nameserver by resolving `dns_selftest_name`,
+	/// rebuilding the rotation when any enters or leaves quarantine.
+	pub async fn probe_quarantine(&self) -> Result<()> {
+		let config = &self.services.server.config;
+		let threshold = config.dns_quarantine_threshold;
+		let results = self
+			.resolver
+			.probe_nameservers(&config.dns_selftest_name)
+			.await?;
+
+		let mut changed = false;
+		for (nameserver, elapsed, result) in results {
+			let latency = result.is_ok().then_some(elapsed);
+			changed |= self.quarantine.record(threshold, &nameserver, latency);
+		}
+
+		if changed {
+			self.resolver
+				.exclude_nameservers(&self.quarantine.quarantined());
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,92 @@
+use std::time::{Duration, Instant};
+
+use conduwuit::{error, info, Result};
+use futures::future::join_all;
+use ruma::ServerName;
+
+use super::fed::FedDest;
+
+/// Outcome of resolving one self-test target.
+#[derive(Debug)]
+pub struct SelfTest {
+	/// What the target is: our own name, the public name, or a peer.
+	pub kind: &'static str,
+	pub target: String,
+	pub elapsed: Duration,
+
+	/// Description of what the target resolved to.
+	pub result: Result<String>,
+}
+
+impl super::Service {
+	/// Resolves our own server name, the public `dns_selftest_name`, and each
+	/// of the `trusted_servers` as a federation destination, bypassing the
+	/// caches. Unlike probing the nameservers this validates the resolution
+	/// of actual federation targets, including their .well-known delegation.
+	pub async fn self_test(&self) -> Vec<SelfTest> {
+		let config = &self.services.server.config;
+		let own = self.self_test_name("own", self.services.server.name.host());
+		let public = self.self_test_name("public", &config.dns_selftest_name);
+		let peers = join_all(
+			config
+				.trusted_servers
+				.iter()
+				.map(|peer| self.self_test_peer(peer)),
+		);
+
+		let (own, public, peers) = futures::join!(own, public, peers);
+		[own, public].into_iter().chain(peers).collect()
+	}
+
+	/// Runs the self-test, logging each failure as an error.
+	pub async fn self_test_logged(&self) -> Vec<SelfTest> {
+		let results = self.self_test().await;
+		for SelfTest { kind, target, elapsed, result } in &results {
+			match result {
+				| Ok(resolved) =>
+					info!(kind, %target, ?elapsed, "DNS self-test passed: {resolved}"),
+				| Err(e) => error!(kind, %target, ?elapsed, "DNS self-test failed: {e}"),
+			}
+		}
+
+		results
+	}
+
+	async fn self_test_name(&self, kind: &'static str, name: &str) -> SelfTest {
+		let timer = Instant::now();
+		let result = self
+			.resolver
+			.lookup_ips(name)
+			.await
+			.map(|ips| format!("{ips:?}"));
+
+		SelfTest {
+			kind,
+			target: name.to_owned(),
+			elapsed: timer.elapsed(),
+			result,
+		}
+	}
+
+	async fn self_test_peer(&self, peer: &ServerName) -> SelfTest {
+		let timer = Instant::now();
+		let result = match self.resolve_actual_dest(peer, false).await {
+			| Ok(cached) => match &cached.dest {
+				| FedDest::Named(host, _) => self
+					.resolver
+					.lookup_ips(host)
+					.await
+					.map(|ips| format!("{} {ips:?}", cached.dest)),
+				| FedDest::Literal(_) => Ok(cached.dest.to_string()),
+			},
+			| Err(e) => Err(e),
+		};
+
+		SelfTest {
+			kind: "peer",
+			target: peer.to_string(),
+			elapsed: timer.elapsed(),
+			result,
+		}
+	}
+}
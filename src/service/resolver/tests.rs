@@ -1,6 +1,33 @@
 #![cfg(test)]
 
-use super::fed::{add_port_to_hostname, get_ip_with_port, FedDest};
+use std::{
+	future::ready,
+	net::{IpAddr, SocketAddr},
+	time::{Duration, Instant},
+};
+
+use hickory_resolver::{
+	error::{ResolveError, ResolveErrorKind},
+	proto::{
+		op::{Query, ResponseCode},
+		rr::{Name, RecordType},
+	},
+};
+
+use super::{
+	actual::{walk_srv, SrvDest},
+	breaker::Circuit,
+	cache::override_key,
+	dns::{
+		cap_per_family, interleave_families, ip_literal, prefer_family, rule_matches,
+		zone_matches,
+	},
+	failure::ResolveFailure,
+	fed::{add_port_to_hostname, get_ip_with_port, hostname_with_port, FedDest},
+	hints::prefer_addr,
+	mock::{lookup, Mock},
+	quarantine::Quarantine,
+};
 
 #[test]
 fn ips_get_default_ports() {
@@ -41,3 +68,309 @@ fn hostnames_keep_custom_ports() {
 		FedDest::Named(String::from("example.com"), ":1337".try_into().unwrap())
 	);
 }
+
+#[test]
+fn hostnames_fall_back_to_configured_port() {
+	assert_eq!(
+		hostname_with_port("example.com", 8008),
+		FedDest::Named(String::from("example.com"), ":8008".try_into().unwrap())
+	);
+	assert_eq!(hostname_with_port("example.com", 8448), add_port_to_hostname("example.com"));
+}
+
+#[test]
+fn mock_serves_known_names() {
+	let addr = "127.0.0.1:8448".parse().unwrap();
+	let mock = Mock::from([("example.com".to_owned(), vec![addr])]);
+	assert_eq!(lookup(&mock, "example.com").unwrap(), vec![addr]);
+}
+
+#[test]
+fn mock_rejects_unknown_names() {
+	let mock = Mock::new();
+	lookup(&mock, "example.com").unwrap_err();
+}
+
+#[test]
+fn ip_strategy_rules_match() {
+	assert!(rule_matches("example.org", "example.org"), "exact pattern matches the name");
+	assert!(!rule_matches("example.org", "matrix.example.org"), "exact pattern is exact");
+	assert!(
+		rule_matches("*.example.org", "matrix.example.org"),
+		"wildcard matches subdomains"
+	);
+	assert!(rule_matches("*.example.org", "a.b.example.org"), "wildcard matches any depth");
+	assert!(!rule_matches("*.example.org", "example.org"), "wildcard excludes the domain");
+	assert!(!rule_matches("*.example.org", "badexample.org"), "wildcard respects labels");
+}
+
+#[test]
+fn socket_addrs_named() {
+	let dest = FedDest::Named("example.com".to_owned(), ":8449".try_into().unwrap());
+	let ips = ["1.1.1.1".parse().unwrap(), "dead::beef".parse().unwrap()];
+	assert_eq!(
+		dest.socket_addrs(ips),
+		["1.1.1.1:8449".parse().unwrap(), "[dead::beef]:8449".parse().unwrap()],
+		"named destination keeps resolution order with its port"
+	);
+}
+
+#[test]
+fn socket_addrs_literal() {
+	let dest = FedDest::Literal("1.1.1.1:8448".parse().unwrap());
+	assert_eq!(
+		dest.socket_addrs(None),
+		["1.1.1.1:8448".parse().unwrap()],
+		"literal destination needs no resolution"
+	);
+}
+
+#[test]
+fn override_key_case_insensitive() {
+	assert_eq!(override_key("Matrix.Example.Org"), "matrix.example.org", "uppercase is folded");
+	assert_eq!(override_key("matrix.example.org"), "matrix.example.org", "lowercase is kept");
+}
+
+#[test]
+fn interleave_families_starts_with_ipv6() {
+	let ips = ["1.1.1.1", "2.2.2.2", "3.3.3.3", "dead::beef", "dead::cafe"]
+		.map(|ip| ip.parse().unwrap());
+
+	let expected = ["dead::beef", "1.1.1.1", "dead::cafe", "2.2.2.2", "3.3.3.3"]
+		.map(|ip| ip.parse().unwrap());
+
+	assert_eq!(interleave_families(ips.into_iter()), expected);
+}
+
+#[test]
+fn zones_match_themselves_and_subdomains() {
+	assert!(zone_matches("corp.internal", "corp.internal"));
+	assert!(zone_matches("corp.internal", "matrix.corp.internal"));
+	assert!(zone_matches("corp.internal", "a.b.corp.internal"));
+	assert!(!zone_matches("corp.internal", "evilcorp.internal"));
+	assert!(!zone_matches("corp.internal", "internal"));
+}
+
+#[test]
+fn prefer_addr_moves_hint_to_front() {
+	let mut addrs: [SocketAddr; 3] = [
+		"[2001:db8::1]:0".parse().unwrap(),
+		"192.0.2.1:0".parse().unwrap(),
+		"192.0.2.2:0".parse().unwrap(),
+	];
+
+	prefer_addr(&mut addrs, "192.0.2.2".parse().unwrap());
+	assert_eq!(addrs.map(|addr| addr.ip().to_string()), [
+		"192.0.2.2",
+		"2001:db8::1",
+		"192.0.2.1"
+	]);
+
+	prefer_addr(&mut addrs, "198.51.100.1".parse().unwrap());
+	assert_eq!(addrs.map(|addr| addr.ip().to_string()), [
+		"192.0.2.2",
+		"2001:db8::1",
+		"192.0.2.1"
+	]);
+}
+
+#[test]
+fn cap_per_family_keeps_each_family() {
+	let ips: [IpAddr; 5] = [
+		"192.0.2.1".parse().unwrap(),
+		"192.0.2.2".parse().unwrap(),
+		"192.0.2.3".parse().unwrap(),
+		"2001:db8::1".parse().unwrap(),
+		"192.0.2.4".parse().unwrap(),
+	];
+
+	let capped: Vec<_> = cap_per_family(ips.into_iter(), 2).collect();
+	assert_eq!(capped, [ips[0], ips[1], ips[3]]);
+
+	let uncapped: Vec<_> = cap_per_family(ips.into_iter(), 0).collect();
+	assert_eq!(uncapped, ips);
+}
+
+#[test]
+fn prefer_family_sorts_stably() {
+	let ips: [IpAddr; 4] =
+		["dead::beef", "1.1.1.1", "dead::cafe", "2.2.2.2"].map(|ip| ip.parse().unwrap());
+
+	let mut v4 = ips;
+	prefer_family(&mut v4, "v4");
+	let expected: [IpAddr; 4] =
+		["1.1.1.1", "2.2.2.2", "dead::beef", "dead::cafe"].map(|ip| ip.parse().unwrap());
+	assert_eq!(v4, expected);
+
+	let mut v6 = ips;
+	prefer_family(&mut v6, "v6");
+	let expected: [IpAddr; 4] =
+		["dead::beef", "dead::cafe", "1.1.1.1", "2.2.2.2"].map(|ip| ip.parse().unwrap());
+	assert_eq!(v6, expected);
+
+	let mut none = ips;
+	prefer_family(&mut none, "none");
+	assert_eq!(none, ips);
+}
+
+#[test]
+fn ip_literals_skip_resolution() {
+	assert_eq!(ip_literal("1.1.1.1"), Some("1.1.1.1:0".parse().unwrap()));
+	assert_eq!(ip_literal("1.1.1.1:8448"), Some("1.1.1.1:8448".parse().unwrap()));
+	assert_eq!(ip_literal("2001:db8::1"), Some("[2001:db8::1]:0".parse().unwrap()));
+	assert_eq!(ip_literal("[2001:db8::1]"), Some("[2001:db8::1]:0".parse().unwrap()));
+	assert_eq!(ip_literal("[2001:db8::1]:8448"), Some("[2001:db8::1]:8448".parse().unwrap()));
+	assert_eq!(ip_literal("example.com"), None);
+	assert_eq!(ip_literal("example.com:8448"), None);
+}
+
+#[test]
+fn quarantine_after_threshold_until_success() {
+	let quarantine = Quarantine::default();
+	let ns = "192.0.2.53:53/udp";
+
+	assert!(!quarantine.record(2, ns, None));
+	assert!(quarantine.quarantined().is_empty());
+	assert!(quarantine.record(2, ns, None));
+	assert!(quarantine.quarantined().contains(ns));
+	assert!(!quarantine.record(2, ns, None));
+	assert!(quarantine.record(2, ns, Some(Duration::from_millis(5))));
+	assert!(quarantine.quarantined().is_empty());
+}
+
+fn no_records(response_code: ResponseCode) -> ResolveError {
+	let query = Query::query(Name::from_ascii("example.com.").unwrap(), RecordType::A);
+	ResolveError::nx_error(query, None, None, response_code, true)
+}
+
+#[test]
+fn resolve_failures_are_classified() {
+	let cases = [
+		(no_records(ResponseCode::NXDomain), ResolveFailure::NxDomain),
+		(no_records(ResponseCode::NoError), ResolveFailure::NoRecords),
+		(no_records(ResponseCode::ServFail), ResolveFailure::ServFail),
+		(no_records(ResponseCode::Refused), ResolveFailure::ServFail),
+		(ResolveErrorKind::Timeout.into(), ResolveFailure::Timeout),
+		(ResolveErrorKind::NoConnections.into(), ResolveFailure::NoConnections),
+		(ResolveErrorKind::Message("malformed").into(), ResolveFailure::Other),
+	];
+
+	for (e, expected) in cases {
+		assert_eq!(ResolveFailure::classify(&e), expected, "{e}");
+		assert_eq!(ResolveFailure::find(&e), Some(expected));
+	}
+
+	assert!(ResolveFailure::NxDomain.is_permanent());
+	assert!(!ResolveFailure::ServFail.is_permanent());
+	assert!(!ResolveFailure::Timeout.is_permanent());
+}
+
+const WINDOW: Duration = Duration::from_secs(60);
+const COOLDOWN: Duration = Duration::from_secs(300);
+
+fn after(now: Instant, secs: u64) -> Instant {
+	now.checked_add(Duration::from_secs(secs)).unwrap()
+}
+
+fn opened_circuit(now: Instant) -> Circuit {
+	let mut circuit = Circuit::new(now);
+	assert!(!circuit.fail(now, 3, WINDOW, COOLDOWN));
+	assert!(!circuit.fail(now, 3, WINDOW, COOLDOWN));
+	assert!(circuit.fail(now, 3, WINDOW, COOLDOWN), "opens at the threshold");
+	circuit
+}
+
+#[test]
+fn breaker_opens_at_threshold_within_window() {
+	let now = Instant::now();
+	let mut circuit = Circuit::new(now);
+	assert!(!circuit.fail(now, 2, WINDOW, COOLDOWN));
+	assert!(
+		!circuit.fail(after(now, 120), 2, WINDOW, COOLDOWN),
+		"failures outside the window start a new one"
+	);
+	assert!(circuit.permit(after(now, 120), COOLDOWN));
+
+	let mut circuit = opened_circuit(now);
+	assert!(!circuit.permit(now, COOLDOWN));
+	assert!(!circuit.permit(after(now, 299), COOLDOWN));
+}
+
+#[test]
+fn breaker_half_open_permits_a_single_probe() {
+	let now = Instant::now();
+	let mut circuit = opened_circuit(now);
+	assert!(circuit.permit(after(now, 301), COOLDOWN), "probe after the cooldown");
+	assert!(!circuit.permit(after(now, 302), COOLDOWN), "one probe at a time");
+	assert!(
+		circuit.permit(after(now, 601), COOLDOWN),
+		"another probe once the first is presumed lost"
+	);
+}
+
+#[test]
+fn breaker_reopens_on_failed_probe() {
+	let now = Instant::now();
+	let mut circuit = opened_circuit(now);
+	assert!(circuit.permit(after(now, 301), COOLDOWN));
+	assert!(
+		circuit.fail(after(now, 301), 3, WINDOW, COOLDOWN),
+		"a failed probe re-opens the circuit although the window has passed"
+	);
+	assert!(!circuit.permit(after(now, 302), COOLDOWN));
+	assert!(circuit.permit(after(now, 602), COOLDOWN));
+}
+
+/// Walks the SRV forms of example.com answering each from `answers` in turn,
+/// returning the outcome and the forms queried.
+async fn walk_srv_answering(
+	answers: Vec<Result<Option<FedDest>, ResolveError>>,
+) -> (Result<SrvDest, ResolveError>, Vec<String>) {
+	let mut queried = Vec::new();
+	let mut answers = answers.into_iter();
+	let result = walk_srv("example.com", 8008, |form| {
+		queried.push(form);
+		ready(answers.next().expect("an answer for each form queried"))
+	})
+	.await;
+
+	(result, queried)
+}
+
+#[tokio::test]
+async fn srv_servfail_on_either_form_fails() {
+	let (result, queried) =
+		walk_srv_answering(vec![Err(no_records(ResponseCode::ServFail))]).await;
+	assert!(result.is_err(), "SERVFAIL on the first form");
+	assert_eq!(queried, ["_matrix-fed._tcp.example.com"]);
+
+	let (result, queried) = walk_srv_answering(vec![
+		Err(no_records(ResponseCode::NXDomain)),
+		Err(no_records(ResponseCode::ServFail)),
+	])
+	.await;
+	assert!(result.is_err(), "SERVFAIL on the second form");
+	assert_eq!(queried, ["_matrix-fed._tcp.example.com", "_matrix._tcp.example.com"]);
+}
+
+#[tokio::test]
+async fn srv_absent_on_both_forms_falls_back_to_default_port() {
+	let (result, queried) = walk_srv_answering(vec![
+		Err(no_records(ResponseCode::NXDomain)),
+		Err(no_records(ResponseCode::NoError)),
+	])
+	.await;
+	assert_eq!(result.unwrap(), SrvDest::Fallback(hostname_with_port("example.com", 8008)));
+	assert_eq!(queried, ["_matrix-fed._tcp.example.com", "_matrix._tcp.example.com"]);
+}
+
+#[tokio::test]
+async fn srv_record_of_legacy_form_is_used() {
+	let record = hostname_with_port("matrix.example.com", 443);
+	let (result, _) = walk_srv_answering(vec![
+		Err(no_records(ResponseCode::NXDomain)),
+		Ok(Some(record.clone())),
+	])
+	.await;
+	assert_eq!(result.unwrap(), SrvDest::Record(record));
+}
@@ -0,0 +1,132 @@
+use std::{fmt::Write, sync::Arc};
+
+use conduwuit::{utils::time, Result};
+use ruma::ServerName;
+
+use super::{
+	cache::{CachedDest, CachedOverride},
+	fed::get_ip_with_port,
+};
+
+impl super::Service {
+	/// Runs server-name resolution step by step, describing each step in
+	/// turn: the cache layers consulted, the IP literal and explicit port
+	/// checks, .well-known delegation, SRV records, address records and the
+	/// addresses finally chosen. This is for diagnosing federation with a
+	/// server; the .well-known request may populate the caches as usual.
+	pub async fn trace_matrix_destination(
+		self: &Arc<Self>,
+		server_name: &ServerName,
+	) -> Result<String> {
+		let mut out = String::new();
+		let name = server_name.as_str();
+
+		match self.cache.get_destination(server_name).await {
+			| Ok(CachedDest { dest, host, expire }) => {
+				let expire = time::format(expire, "%+");
+				writeln!(out, "Destination cache: hit {dest} (host {host}) until {expire}")?;
+			},
+			| Err(_) => writeln!(out, "Destination cache: miss")?,
+		}
+
+		let target = if let Some(literal) = get_ip_with_port(name) {
+			writeln!(out, "IP literal: {literal}")?;
+			None
+		} else if name.contains(':') {
+			writeln!(out, "IP literal: no; explicit port, skipping .well-known and SRV")?;
+			Some(name.to_owned())
+		} else {
+			writeln!(out, "IP literal: no; no explicit port")?;
+			match self.request_well_known(name).await {
+				| Ok(Some(delegated)) => {
+					writeln!(out, ".well-known: delegated to {delegated}")?;
+					if let Some(literal) = get_ip_with_port(&delegated) {
+						writeln!(out, "Delegated IP literal: {literal}")?;
+						None
+					} else {
+						Some(delegated)
+					}
+				},
+				| Ok(None) => {
+					writeln!(out, ".well-known: none")?;
+					Some(name.to_owned())
+				},
+				| Err(e) => {
+					writeln!(out, ".well-known: failed: {e}")?;
+					Some(name.to_owned())
+				},
+			}
+		};
+
+		if let Some(target) = target {
+			self.trace_hostname(&mut out, &target).await?;
+		}
+
+		match self.resolve_matrix_destination(server_name).await {
+			| Ok(addrs) => writeln!(out, "Chosen addresses: {addrs:?}")?,
+			| Err(e) => writeln!(out, "Resolution failed: {e}")?,
+		}
+
+		Ok(out)
+	}
+
+	async fn trace_hostname(&self, out: &mut String, target: &str) -> Result {
+		let mut hostname = target
+			.split_once(':')
+			.map_or(target, |(host, _)| host)
+			.to_owned();
+
+		if !target.contains(':') {
+			for srv in [format!("_matrix-fed._tcp.{target}"), format!("_matrix._tcp.{target}")] {
+				match self
+					.resolver
+					.srv_resolver_for(&srv)
+					.srv_lookup(srv.as_str())
+					.await
+				{
+					| Ok(records) => {
+						for record in records.iter() {
+							let (priority, weight) = (record.priority(), record.weight());
+							let (port, srv_target) = (record.port(), record.target());
+							writeln!(
+								out,
+								"SRV {srv}: {srv_target}:{port} (priority {priority}, weight \
+								 {weight})"
+							)?;
+						}
+
+						if let Some(record) = records.iter().next() {
+							hostname =
+								record.target().to_string().trim_end_matches('.').to_owned();
+							break;
+						}
+					},
+					| Err(e) => writeln!(out, "SRV {srv}: {e}")?,
+				}
+			}
+		}
+
+		match self.cache.get_override(target).await {
+			| Ok(CachedOverride { ips, port, expire, overriding, .. }) => {
+				let expire = time::format(expire, "%+");
+				writeln!(
+					out,
+					"Override cache: hit {ips:?} port {port} until {expire} (overriding \
+					 {overriding:?})"
+				)?;
+			},
+			| Err(_) => writeln!(out, "Override cache: miss")?,
+		}
+
+		match self.resolver.lookup_ips(&hostname).await {
+			| Ok(ips) => writeln!(out, "A/AAAA {hostname}: {ips:?}")?,
+			| Err(e) => writeln!(out, "A/AAAA {hostname}: {e}")?,
+		}
+
+		if self.breaker.is_open(&hostname) {
+			writeln!(out, "Circuit breaker: open for {hostname}")?;
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,100 @@
+use std::{
+	collections::HashMap,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use conduwuit::{debug_warn, info, utils::stream::IterStream, Result};
+use futures::StreamExt;
+use ruma::{OwnedServerName, ServerName};
+
+/// Destinations resolved at once while warming the caches.
+const WARM_CONCURRENCY: usize = 16;
+
+/// Outcome of warming the caches for one peer.
+#[derive(Debug)]
+pub struct Warmed {
+	pub server: OwnedServerName,
+	pub elapsed: Duration,
+
+	/// Description of what the peer resolved to.
+	pub result: Result<String>,
+}
+
+impl super::Service {
+	/// Resolves each server as a federation destination through the live
+	/// resolver, as sending to it would, so that its destination and addresses
+	/// are cached ahead of the first request.
+	pub async fn warm(self: &Arc<Self>, servers: Vec<OwnedServerName>) -> Vec<Warmed> {
+		servers
+			.into_iter()
+			.stream()
+			.map(|server| self.warm_one(server))
+			.buffer_unordered(WARM_CONCURRENCY)
+			.collect()
+			.await
+	}
+
+	/// Warms the caches for up to `limit` of the servers sharing the most
+	/// rooms with us, logging the outcome.
+	pub async fn warm_most_federated(self: &Arc<Self>, limit: usize) -> Vec<Warmed> {
+		let servers = self.most_federated(limit).await;
+		let results = self.warm(servers).await;
+		let failed = results
+			.iter()
+			.filter(|warmed| warmed.result.is_err())
+			.count();
+		for Warmed { server, elapsed, result } in &results {
+			if let Err(e) = result {
+				debug_warn!(%server, ?elapsed, "Failed to warm destination: {e}");
+			}
+		}
+
+		info!(warmed = results.len(), failed, "Warmed destinations of most federated servers");
+		results
+	}
+
+	/// Up to `limit` remote servers in the most rooms we are joined to, most
+	/// rooms first.
+	pub async fn most_federated(&self, limit: usize) -> Vec<OwnedServerName> {
+		let state_cache = &self.services.state_cache;
+		let own: &ServerName = &self.services.server.name;
+		let rooms: Vec<_> = state_cache
+			.server_rooms(own)
+			.map(ToOwned::to_owned)
+			.collect()
+			.await;
+
+		let mut counts: HashMap<OwnedServerName, usize> = HashMap::new();
+		for room_id in &rooms {
+			let mut servers = state_cache.room_servers(room_id).boxed();
+			while let Some(server) = servers.next().await {
+				if server != own {
+					let count = counts.entry(server.to_owned()).or_default();
+					*count = count.saturating_add(1);
+				}
+			}
+		}
+
+		let mut servers: Vec<_> = counts.into_iter().collect();
+		servers.sort_unstable_by(|(a, a_count), (b, b_count)| {
+			b_count.cmp(a_count).then_with(|| a.cmp(b))
+		});
+
+		servers
+			.into_iter()
+			.take(limit)
+			.map(|(server, _)| server)
+			.collect()
+	}
+
+	async fn warm_one(self: &Arc<Self>, server: OwnedServerName) -> Warmed {
+		let timer = Instant::now();
+		let result = self
+			.resolve_matrix_destination(&server)
+			.await
+			.map(|addrs| format!("{addrs:?}"));
+
+		Warmed { server, elapsed: timer.elapsed(), result }
+	}
+}
@@ -11,7 +11,7 @@
 
 pub(super) struct Data {
 	shorteventid_authchain: Arc<Map>,
-	pub(super) auth_chain_cache: Mutex<LruCache<Vec<u64>, Arc<[ShortEventId]>>>,
+	pub(super) auth_chain_cache: Mutex<LruCache<Vec<ShortEventId>, Arc<[ShortEventId]>>>,
 }
 
 impl Data {
@@ -29,7 +29,7 @@ pub(super) fn new(args: &crate::Args<'_>) -> Self {
 
 	pub(super) async fn get_cached_eventid_authchain(
 		&self,
-		key: &[u64],
+		key: &[ShortEventId],
 	) -> Result<Arc<[ShortEventId]>> {
 		debug_assert!(!key.is_empty(), "auth_chain key must not be empty");
 
@@ -58,7 +58,8 @@ pub(super) async fn get_cached_eventid_authchain(
 		let chain = chain
 			.chunks_exact(size_of::<u64>())
 			.map(utils::u64_from_u8)
-			.collect::<Arc<[u64]>>();
+			.map(ShortEventId::from)
+			.collect::<Arc<[ShortEventId]>>();
 
 		// Cache in RAM
 		self.auth_chain_cache
@@ -69,7 +70,11 @@ pub(super) async fn get_cached_eventid_authchain(
 		Ok(chain)
 	}
 
-	pub(super) fn cache_auth_chain(&self, key: Vec<u64>, auth_chain: Arc<[ShortEventId]>) {
+	pub(super) fn cache_auth_chain(
+		&self,
+		key: Vec<ShortEventId>,
+		auth_chain: Arc<[ShortEventId]>,
+	) {
 		debug_assert!(!key.is_empty(), "auth_chain key must not be empty");
 
 		// Only persist single events in db
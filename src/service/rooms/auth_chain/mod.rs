@@ -31,7 +31,7 @@ struct Services {
 	timeline: Dep<rooms::timeline::Service>,
 }
 
-type Bucket<'a> = BTreeSet<(u64, &'a EventId)>;
+type Bucket<'a> = BTreeSet<(ShortEventId, &'a EventId)>;
 
 impl crate::Service for Service {
 	fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
@@ -89,7 +89,8 @@ pub async fn get_auth_chain<'a, I>(
 
 	let mut buckets = [BUCKET; NUM_BUCKETS];
 	while let Some((short, starting_event)) = starting_ids.next().await {
-		let bucket: usize = short.try_into()?;
+		let short = short?;
+		let bucket: usize = short.get().try_into()?;
 		let bucket: usize = validated!(bucket % NUM_BUCKETS);
 		buckets[bucket].insert((short, starting_event));
 	}
@@ -209,7 +210,7 @@ async fn get_auth_chain_inner(
 						.services
 						.short
 						.get_or_create_shorteventid(auth_event)
-						.await;
+						.await?;
 
 					if found.insert(sauthevent) {
 						trace!(?event_id, ?auth_event, "adding auth event to processing queue");
@@ -226,13 +227,16 @@ async fn get_auth_chain_inner(
 
 #[implement(Service)]
 #[inline]
-pub async fn get_cached_eventid_authchain(&self, key: &[u64]) -> Result<Arc<[ShortEventId]>> {
+pub async fn get_cached_eventid_authchain(
+	&self,
+	key: &[ShortEventId],
+) -> Result<Arc<[ShortEventId]>> {
 	self.db.get_cached_eventid_authchain(key).await
 }
 
 #[implement(Service)]
 #[tracing::instrument(skip_all, level = "debug")]
-pub fn cache_auth_chain(&self, key: Vec<u64>, auth_chain: &HashSet<ShortEventId>) {
+pub fn cache_auth_chain(&self, key: Vec<ShortEventId>, auth_chain: &HashSet<ShortEventId>) {
 	let val: Arc<[ShortEventId]> = auth_chain.iter().copied().collect();
 
 	self.db.cache_auth_chain(key, val);
@@ -240,7 +244,7 @@ pub fn cache_auth_chain(&self, key: Vec<u64>, auth_chain: &HashSet<ShortEventId>
 
 #[implement(Service)]
 #[tracing::instrument(skip_all, level = "debug")]
-pub fn cache_auth_chain_vec(&self, key: Vec<u64>, auth_chain: &[ShortEventId]) {
+pub fn cache_auth_chain_vec(&self, key: Vec<ShortEventId>, auth_chain: &[ShortEventId]) {
 	let val: Arc<[ShortEventId]> = auth_chain.iter().copied().collect();
 
 	self.db.cache_auth_chain(key, val);
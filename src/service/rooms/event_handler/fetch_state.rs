@@ -24,7 +24,7 @@ pub(super) async fn fetch_state(
 	create_event: &PduEvent,
 	room_id: &RoomId,
 	event_id: &EventId,
-) -> Result<Option<HashMap<u64, OwnedEventId>>> {
+) -> Result<Option<HashMap<ShortStateKey, OwnedEventId>>> {
 	let res = self
 		.services
 		.sending
@@ -52,7 +52,7 @@ pub(super) async fn fetch_state(
 			.services
 			.short
 			.get_or_create_shortstatekey(&pdu.kind.to_string().into(), &state_key)
-			.await;
+			.await?;
 
 		match state.entry(shortstatekey) {
 			| hash_map::Entry::Vacant(v) => {
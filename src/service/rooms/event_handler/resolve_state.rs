@@ -15,7 +15,7 @@
 	OwnedEventId, RoomId, RoomVersionId,
 };
 
-use crate::rooms::state_compressor::CompressedState;
+use crate::rooms::{short::ShortStateKey, state_compressor::CompressedState};
 
 #[implement(super::Service)]
 #[tracing::instrument(name = "resolve", level = "debug", skip_all)]
@@ -23,7 +23,7 @@ pub async fn resolve_state(
 	&self,
 	room_id: &RoomId,
 	room_version_id: &RoomVersionId,
-	incoming_state: HashMap<u64, OwnedEventId>,
+	incoming_state: HashMap<ShortStateKey, OwnedEventId>,
 ) -> Result<Arc<CompressedState>> {
 	trace!("Loading current room state ids");
 	let current_sstatehash = self
@@ -85,10 +85,10 @@ pub async fn resolve_state(
 			self.services
 				.short
 				.get_or_create_shortstatekey(event_type, state_key)
-				.map(move |shortstatekey| (shortstatekey, event_id))
+				.map_ok(move |shortstatekey| (shortstatekey, event_id))
 		})
-		.collect()
-		.await;
+		.try_collect()
+		.await?;
 
 	trace!("Compressing state...");
 	let new_room_state: CompressedState = self
@@ -99,8 +99,8 @@ pub async fn resolve_state(
 				.iter()
 				.map(|(ref ssk, eid)| (ssk, (*eid).borrow())),
 		)
-		.collect()
-		.await;
+		.try_collect()
+		.await?;
 
 	Ok(Arc::new(new_room_state))
 }
@@ -13,7 +13,7 @@
 use futures::{future::try_join, FutureExt, StreamExt, TryFutureExt, TryStreamExt};
 use ruma::{state_res::StateMap, OwnedEventId, RoomId, RoomVersionId};
 
-use crate::rooms::short::ShortStateHash;
+use crate::rooms::short::{ShortStateHash, ShortStateKey};
 
 // TODO: if we know the prev_events of the incoming event we can avoid the
 #[implement(super::Service)]
@@ -22,7 +22,7 @@
 pub(super) async fn state_at_incoming_degree_one(
 	&self,
 	incoming_pdu: &Arc<PduEvent>,
-) -> Result<Option<HashMap<u64, OwnedEventId>>> {
+) -> Result<Option<HashMap<ShortStateKey, OwnedEventId>>> {
 	let prev_event = &incoming_pdu.prev_events[0];
 	let Ok(prev_event_sstatehash) = self
 		.services
@@ -53,7 +53,7 @@ pub(super) async fn state_at_incoming_degree_one(
 			.services
 			.short
 			.get_or_create_shortstatekey(&prev_pdu.kind.to_string().into(), state_key)
-			.await;
+			.await?;
 
 		state.insert(shortstatekey, prev_event.clone());
 		// Now it's the state after the pdu
@@ -71,7 +71,7 @@ pub(super) async fn state_at_incoming_resolved(
 	incoming_pdu: &Arc<PduEvent>,
 	room_id: &RoomId,
 	room_version_id: &RoomVersionId,
-) -> Result<Option<HashMap<u64, OwnedEventId>>> {
+) -> Result<Option<HashMap<ShortStateKey, OwnedEventId>>> {
 	trace!("Calculating extremity statehashes...");
 	let Ok(extremity_sstatehashes) = incoming_pdu
 		.prev_events
@@ -123,12 +123,11 @@ pub(super) async fn state_at_incoming_resolved(
 			self.services
 				.short
 				.get_or_create_shortstatekey(&event_type, &state_key)
-				.map(move |shortstatekey| (shortstatekey, event_id))
+				.map_ok(move |shortstatekey| (shortstatekey, event_id))
 				.await
 		})
-		.collect()
-		.map(Some)
-		.map(Ok)
+		.try_collect()
+		.map_ok(Some)
 		.await
 }
 
@@ -151,7 +150,7 @@ async fn state_at_incoming_fork(
 			.services
 			.short
 			.get_or_create_shortstatekey(&prev_event.kind.to_string().into(), state_key)
-			.await;
+			.await?;
 
 		let event_id = &prev_event.event_id;
 		leaf_state.insert(shortstatekey, event_id.clone());
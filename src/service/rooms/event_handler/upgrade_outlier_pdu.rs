@@ -5,7 +5,7 @@
 	utils::stream::{BroadbandExt, ReadyExt},
 	warn, Err, PduEvent, Result,
 };
-use futures::{future::ready, FutureExt, StreamExt};
+use futures::{future::ready, StreamExt, TryFutureExt, TryStreamExt};
 use ruma::{
 	events::StateEventType,
 	state_res::{self, EventTypeExt},
@@ -179,9 +179,9 @@ pub(super) async fn upgrade_outlier_to_timeline_pdu(
 				.iter()
 				.map(|(ssk, eid)| (ssk, eid.borrow())),
 		)
-		.collect()
-		.map(Arc::new)
-		.await;
+		.try_collect()
+		.map_ok(Arc::new)
+		.await?;
 
 	if incoming_pdu.state_key.is_some() {
 		debug!("Event is a state-event. Deriving new room state");
@@ -193,7 +193,7 @@ pub(super) async fn upgrade_outlier_to_timeline_pdu(
 				.services
 				.short
 				.get_or_create_shortstatekey(&incoming_pdu.kind.to_string().into(), state_key)
-				.await;
+				.await?;
 
 			let event_id = &incoming_pdu.event_id;
 			state_after.insert(shortstatekey, event_id.clone());
@@ -54,7 +54,9 @@ pub async fn get_relations(
 		max_depth: u8,
 		dir: Direction,
 	) -> Vec<PdusIterItem> {
-		let room_id = self.services.short.get_or_create_shortroomid(room_id).await;
+		let Ok(room_id) = self.services.short.get_shortroomid(room_id).await else {
+			return Vec::new();
+		};
 
 		let target = match self.services.timeline.get_pdu_count(target).await {
 			| Ok(PduCount::Normal(c)) => c,
@@ -64,7 +66,7 @@ pub async fn get_relations(
 
 		let mut pdus: Vec<_> = self
 			.db
-			.get_relations(user_id, room_id, target, from, dir)
+			.get_relations(user_id, room_id, target.into(), from, dir)
 			.collect()
 			.await;
 
@@ -79,7 +81,7 @@ pub async fn get_relations(
 
 			let relations: Vec<_> = self
 				.db
-				.get_relations(user_id, room_id, target, from, dir)
+				.get_relations(user_id, room_id, target.into(), from, dir)
 				.collect()
 				.await;
 
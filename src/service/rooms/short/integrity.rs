@@ -0,0 +1,188 @@
+use std::{sync::Arc, time::SystemTime};
+
+use conduwuit::{
+	debug, error, implement, info,
+	utils::{rand, stream::TryIgnore},
+	warn, Result,
+};
+use database::Map;
+use futures::{pin_mut, StreamExt};
+
+use super::Service;
+
+/// Outcome of sampling the short ID tables for entries whose reverse mapping
+/// has no matching forward mapping.
+#[derive(Clone, Copy, Debug)]
+pub struct IntegrityScan {
+	pub at: SystemTime,
+	pub checked: usize,
+	pub mismatches: usize,
+}
+
+/// Outcome of repairing the short ID tables, or of a dry run thereof.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Repair {
+	/// Whether the fixes were written, rather than only counted.
+	pub applied: bool,
+	pub checked: usize,
+	/// Forward mappings recreated from a reverse entry lacking one.
+	pub forward_recreated: usize,
+	/// Reverse mappings recreated from a forward entry lacking one.
+	pub reverse_recreated: usize,
+	/// Reverse entries removed as their key's forward mapping and its own
+	/// reverse entry agree on another short ID.
+	pub reverse_removed: usize,
+	/// Entries contradicting each other where either side could be the
+	/// orphan; these are logged but left alone.
+	pub conflicts: usize,
+}
+
+/// Samples up to `sample` consecutive entries, from a random starting short
+/// ID, of each table mapping short IDs back to what they were allocated for,
+/// and checks the forward table maps each back to the same short ID. Every
+/// mismatch is logged as an error. The result is retained for
+/// `last_integrity_scan`.
+#[implement(Service)]
+pub async fn scan_integrity(&self, sample: usize) -> Result<IntegrityScan> {
+	let max = self.services.globals.current_count()?;
+	let (mut checked, mut mismatches) = (0_usize, 0_usize);
+	for (table, reverse, forward) in self.paired_tables() {
+		let start = rand::number(0..max.saturating_add(1)).to_be_bytes();
+		let entries: Vec<(Vec<u8>, Vec<u8>)> = reverse
+			.raw_stream_from(&start)
+			.ignore_err()
+			.take(sample)
+			.map(|(short, key)| (short.to_vec(), key.to_vec()))
+			.collect()
+			.await;
+
+		for (short, key) in entries {
+			let consistent = forward
+				.get(&key)
+				.await
+				.is_ok_and(|found| found.as_ref() == short.as_slice());
+
+			if !consistent {
+				error!(table, ?short, ?key, "Short ID has no matching forward mapping");
+				mismatches = mismatches.saturating_add(1);
+			}
+
+			checked = checked.saturating_add(1);
+		}
+	}
+
+	let scan = IntegrityScan {
+		at: SystemTime::now(),
+		checked,
+		mismatches,
+	};
+
+	if mismatches > 0 {
+		error!(checked, mismatches, "Short ID tables are inconsistent");
+	} else {
+		debug!(checked, "Short ID tables are consistent");
+	}
+
+	*self.last_scan.write()? = Some(scan);
+	Ok(scan)
+}
+
+/// The result of the most recent `scan_integrity`, if any since startup.
+#[implement(Service)]
+#[must_use]
+pub fn last_integrity_scan(&self) -> Option<IntegrityScan> {
+	*self.last_scan.read().expect("locked for reading")
+}
+
+/// Scans the whole of each pair of short ID tables for entries orphaned by a
+/// non-atomic write: the missing side is recreated from the one present, and
+/// a reverse entry superseded by another is removed. Nothing is written
+/// unless `apply`; either way each orphan is logged.
+#[implement(Service)]
+pub async fn repair(&self, apply: bool) -> Result<Repair> {
+	let mut repair = Repair { applied: apply, ..Repair::default() };
+	for (table, reverse, forward) in self.paired_tables() {
+		let entries = reverse.raw_stream().ignore_err();
+		pin_mut!(entries);
+		while let Some((short, key)) = entries.next().await {
+			let (short, key) = (short.to_vec(), key.to_vec());
+			repair.checked = repair.checked.saturating_add(1);
+			match forward.get(&key).await {
+				| Ok(found) if found.as_ref() == short.as_slice() => continue,
+				| Ok(found) => {
+					let superseded = reverse
+						.get(found.as_ref())
+						.await
+						.is_ok_and(|other| other.as_ref() == key.as_slice());
+
+					if !superseded {
+						warn!(table, ?short, ?key, "Conflicting short ID mappings");
+						repair.conflicts = repair.conflicts.saturating_add(1);
+						continue;
+					}
+
+					info!(table, ?short, ?key, apply, "Removing superseded reverse entry");
+					repair.reverse_removed = repair.reverse_removed.saturating_add(1);
+					if apply {
+						reverse.remove(&short);
+					}
+				},
+				| Err(e) if e.is_not_found() => {
+					info!(table, ?short, ?key, apply, "Recreating missing forward mapping");
+					repair.forward_recreated = repair.forward_recreated.saturating_add(1);
+					if apply {
+						forward.insert(&key, &short);
+					}
+				},
+				| Err(e) => return Err(e),
+			}
+		}
+
+		let entries = forward.raw_stream().ignore_err();
+		pin_mut!(entries);
+		while let Some((key, short)) = entries.next().await {
+			let (key, short) = (key.to_vec(), short.to_vec());
+			repair.checked = repair.checked.saturating_add(1);
+			match reverse.get(&short).await {
+				| Ok(found) if found.as_ref() == key.as_slice() => continue,
+				| Ok(_) => {
+					warn!(table, ?short, ?key, "Conflicting short ID mappings");
+					repair.conflicts = repair.conflicts.saturating_add(1);
+				},
+				| Err(e) if e.is_not_found() => {
+					info!(table, ?short, ?key, apply, "Recreating missing reverse mapping");
+					repair.reverse_recreated = repair.reverse_recreated.saturating_add(1);
+					if apply {
+						reverse.insert(&short, &key);
+					}
+				},
+				| Err(e) => return Err(e),
+			}
+		}
+	}
+
+	Ok(repair)
+}
+
+/// Each table mapping short IDs back to what they were allocated for, with
+/// the forward table it mirrors.
+#[implement(Service)]
+fn paired_tables(&self) -> [(&'static str, &Arc<Map>, &Arc<Map>); 3] {
+	[
+		(
+			"shorteventid_eventid",
+			&self.db.shorteventid_eventid,
+			&self.db.eventid_shorteventid,
+		),
+		(
+			"shortstatekey_statekey",
+			&self.db.shortstatekey_statekey,
+			&self.db.statekey_shortstatekey,
+		),
+		(
+			"shortstatehash_statehash",
+			&self.db.shortstatehash_statehash,
+			&self.db.statehash_shortstatehash,
+		),
+	]
+}
@@ -1,69 +1,294 @@
-use std::{borrow::Borrow, fmt::Debug, mem::size_of_val, sync::Arc};
+mod integrity;
+mod sizes;
+mod tests;
 
-pub use conduwuit::pdu::{ShortEventId, ShortId, ShortRoomId};
-use conduwuit::{err, implement, utils, utils::IterStream, Result};
-use database::{Deserialized, Get, Map, Qry};
-use futures::{Stream, StreamExt};
-use ruma::{events::StateEventType, EventId, RoomId};
-use serde::Deserialize;
+use std::{
+	borrow::Borrow,
+	collections::HashMap,
+	fmt::{Debug, Write},
+	hash::Hash,
+	mem::size_of_val,
+	ops::Range,
+	sync::{Arc, Mutex, RwLock},
+	time::Duration,
+};
 
+use async_trait::async_trait;
+pub use conduwuit::pdu::{ShortEventId, ShortId, ShortRoomId, ShortStateHash, ShortStateKey};
+use conduwuit::{
+	debug_warn, err, implement, utils,
+	utils::{bytes, math::usize_from_f64, stream::TryIgnore, IterStream, ReadyExt},
+	warn, Err, Result, Server,
+};
+use database::{Database, Deserialized, Get, Map, Qry};
+use futures::{future, stream, Stream, StreamExt, TryStreamExt};
+use lru_cache::LruCache;
+use ruma::{events::StateEventType, EventId, OwnedEventId, OwnedRoomId, RoomId};
+use serde::{
+	de::{value, IntoDeserializer},
+	Deserialize,
+};
+use tokio::{
+	sync::Notify,
+	time::{interval, Interval, MissedTickBehavior},
+};
+
+pub use self::{
+	integrity::{IntegrityScan, Repair},
+	sizes::TableSizes,
+};
 use crate::{globals, Dep};
 
 pub struct Service {
 	db: Data,
+	fallback: Option<Fallback>,
+	rooms: RwLock<RoomIds>,
+	last_scan: RwLock<Option<IntegrityScan>>,
+	last_sizes: RwLock<Option<TableSizes>>,
+	interrupt: Notify,
 	services: Services,
 }
 
+/// Upper bound of shorteventids created by a single write batch.
+const CREATE_BATCH_SIZE: usize = 256;
+
 struct Data {
+	db: Arc<Database>,
 	eventid_shorteventid: Arc<Map>,
 	shorteventid_eventid: Arc<Map>,
 	statekey_shortstatekey: Arc<Map>,
 	shortstatekey_statekey: Arc<Map>,
 	roomid_shortroomid: Arc<Map>,
 	statehash_shortstatehash: Arc<Map>,
+	shortstatehash_statehash: Arc<Map>,
+}
+
+/// Recently resolved event ID mappings, served when reading the database
+/// fails; enabled by `short_id_cache_fallback`.
+struct Fallback {
+	shorteventid_eventid: Mutex<LruCache<ShortEventId, OwnedEventId>>,
+	eventid_shorteventid: Mutex<LruCache<OwnedEventId, ShortEventId>>,
+}
+
+/// Every shortroomid resolved so far in both directions. Rooms are few and
+/// their shortroomid never changes, so these are unbounded and only dropped
+/// when caches are cleared.
+#[derive(Default)]
+struct RoomIds {
+	shortroomid: HashMap<OwnedRoomId, ShortRoomId>,
+	roomid: HashMap<ShortRoomId, OwnedRoomId>,
+}
+
+/// Occupancy of one of the service's in-memory caches.
+#[derive(Clone, Copy, Debug)]
+pub struct CacheStats {
+	pub name: &'static str,
+	pub entries: usize,
+	/// Estimated from the sizes of the cached keys and values.
+	pub bytes: usize,
 }
 
 struct Services {
+	server: Arc<Server>,
 	globals: Dep<globals::Service>,
 }
 
-pub type ShortStateHash = ShortId;
-pub type ShortStateKey = ShortId;
-
+#[async_trait]
 impl crate::Service for Service {
 	fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
+		let config = &args.server.config;
+		let fallback = config
+			.short_id_cache_fallback
+			.then(|| {
+				let capacity = |capacity: u32| {
+					usize_from_f64(f64::from(capacity) * config.cache_capacity_modifier)
+				};
+
+				Result::<_>::Ok(Fallback {
+					shorteventid_eventid: Mutex::new(LruCache::new(capacity(
+						config.shorteventid_cache_capacity,
+					)?)),
+					eventid_shorteventid: Mutex::new(LruCache::new(capacity(
+						config.eventidshort_cache_capacity,
+					)?)),
+				})
+			})
+			.transpose()?;
+
 		Ok(Arc::new(Self {
 			db: Data {
+				db: args.db.clone(),
 				eventid_shorteventid: args.db["eventid_shorteventid"].clone(),
 				shorteventid_eventid: args.db["shorteventid_eventid"].clone(),
 				statekey_shortstatekey: args.db["statekey_shortstatekey"].clone(),
 				shortstatekey_statekey: args.db["shortstatekey_statekey"].clone(),
 				roomid_shortroomid: args.db["roomid_shortroomid"].clone(),
 				statehash_shortstatehash: args.db["statehash_shortstatehash"].clone(),
+				shortstatehash_statehash: args.db["shortstatehash_statehash"].clone(),
 			},
+			fallback,
+			rooms: RwLock::default(),
+			last_scan: RwLock::default(),
+			last_sizes: RwLock::default(),
+			interrupt: Notify::new(),
 			services: Services {
+				server: args.server.clone(),
 				globals: args.depend::<globals::Service>("globals"),
 			},
 		}))
 	}
 
+	async fn worker(self: Arc<Self>) -> Result<()> {
+		let config = &self.services.server.config;
+		let mut scan = ticker(config.short_id_scan_interval);
+		let mut sample = ticker(config.short_id_table_sample_interval);
+		if scan.is_none() && sample.is_none() {
+			return Ok(());
+		}
+
+		loop {
+			tokio::select! {
+				() = self.interrupt.notified() => break,
+				() = tick(scan.as_mut()) => {
+					if let Err(e) = self.scan_integrity(config.short_id_scan_sample).await {
+						warn!("Failed to scan short ID tables: {e}");
+					}
+				},
+				() = tick(sample.as_mut()) => {
+					self.sample_table_sizes().await;
+				},
+			}
+		}
+
+		Ok(())
+	}
+
+	fn interrupt(&self) { self.interrupt.notify_waiters(); }
+
+	fn memory_usage(&self, out: &mut dyn Write) -> Result {
+		for CacheStats { name, entries, bytes } in self.cache_stats()? {
+			let bytes = bytes::pretty(bytes);
+			writeln!(out, "{name}: {entries} ({bytes})")?;
+		}
+
+		Ok(())
+	}
+
+	fn clear_cache(&self) {
+		*self.rooms.write().expect("locked for writing") = RoomIds::default();
+
+		if let Some(fallback) = self.fallback.as_ref() {
+			fallback
+				.shorteventid_eventid
+				.lock()
+				.expect("locked")
+				.clear();
+			fallback
+				.eventid_shorteventid
+				.lock()
+				.expect("locked")
+				.clear();
+		}
+	}
+
 	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
 }
 
+/// An interval ticking every `secs` seconds, first after one period, or none
+/// when `secs` is 0.
+fn ticker(secs: u64) -> Option<Interval> {
+	(secs > 0).then(|| {
+		let period = Duration::from_secs(secs);
+		let mut i = interval(period);
+		i.set_missed_tick_behavior(MissedTickBehavior::Delay);
+		i.reset_after(period);
+		i
+	})
+}
+
+/// Waits for the next tick of `i`, or forever without one.
+async fn tick(i: Option<&mut Interval>) {
+	match i {
+		| Some(i) => _ = i.tick().await,
+		| None => future::pending().await,
+	}
+}
+
+/// Makes the short ID state durable before the database is closed on
+/// shutdown. Allocations and count reservations are written through as they
+/// are made and the caches only mirror the tables, so nothing held in memory
+/// is lost; this syncs the write-ahead log holding the last of those writes.
+#[implement(Service)]
+pub fn flush(&self) -> Result { self.db.db.db.sync() }
+
+/// Entry counts and estimated memory use of each of the service's caches.
+#[implement(Service)]
+pub fn cache_stats(&self) -> Result<Vec<CacheStats>> {
+	const SHORT: usize = size_of::<ShortId>();
+
+	let event_id =
+		|event_id: &EventId| size_of::<OwnedEventId>().saturating_add(event_id.as_str().len());
+	let room_id =
+		|room_id: &RoomId| size_of::<OwnedRoomId>().saturating_add(room_id.as_str().len());
+
+	let rooms = self.rooms.read()?;
+	let mut stats = vec![CacheStats {
+		name: "roomid_shortroomid_cache",
+		entries: rooms.shortroomid.len(),
+		bytes: rooms
+			.shortroomid
+			.keys()
+			.map(|key| room_id(key).saturating_add(SHORT).saturating_mul(2))
+			.sum(),
+	}];
+
+	if let Some(fallback) = self.fallback.as_ref() {
+		let shorteventid_eventid = fallback.shorteventid_eventid.lock()?;
+		stats.push(CacheStats {
+			name: "shorteventid_eventid_fallback",
+			entries: shorteventid_eventid.len(),
+			bytes: shorteventid_eventid
+				.iter()
+				.map(|(_, val)| event_id(val).saturating_add(SHORT))
+				.sum(),
+		});
+
+		let eventid_shorteventid = fallback.eventid_shorteventid.lock()?;
+		stats.push(CacheStats {
+			name: "eventid_shorteventid_fallback",
+			entries: eventid_shorteventid.len(),
+			bytes: eventid_shorteventid
+				.iter()
+				.map(|(key, _)| event_id(key).saturating_add(SHORT))
+				.sum(),
+		});
+	}
+
+	Ok(stats)
+}
+
+/// Returns the shorteventid of an event, allocating one if it has none. Only
+/// for events being persisted into the timeline or room state, or entering an
+/// auth chain, where outliers are first assigned one; read-only paths use
+/// `get_shorteventid` so that merely referencing an event allocates nothing.
 #[implement(Service)]
-pub async fn get_or_create_shorteventid(&self, event_id: &EventId) -> ShortEventId {
+pub async fn get_or_create_shorteventid(&self, event_id: &EventId) -> Result<ShortEventId> {
 	if let Ok(shorteventid) = self.get_shorteventid(event_id).await {
-		return shorteventid;
+		return Ok(shorteventid);
 	}
 
 	self.create_shorteventid(event_id)
 }
 
+/// Batched `get_or_create_shorteventid`; missing shorteventids are allocated
+/// in the order of `event_ids`, so allocation is deterministic for a given
+/// starting count (see `short_id_seed_count`). Each batch reserves its counts
+/// at once, an event repeated within it sharing one.
 #[implement(Service)]
 pub fn multi_get_or_create_shorteventid<'a, I>(
 	&'a self,
 	event_ids: I,
-) -> impl Stream<Item = ShortEventId> + Send + '_
+) -> impl Stream<Item = Result<ShortEventId>> + Send + '_
 where
 	I: Iterator<Item = &'a EventId> + Clone + Debug + Send + 'a,
 {
@@ -72,17 +297,357 @@ pub fn multi_get_or_create_shorteventid<'a, I>(
 		.stream()
 		.get(&self.db.eventid_shorteventid)
 		.zip(event_ids.into_iter().stream())
+		.ready_chunks(CREATE_BATCH_SIZE)
+		.map(|chunk| {
+			let (results, event_ids): (Vec<_>, Vec<_>) = chunk.into_iter().unzip();
+			let stored = results
+				.into_iter()
+				.zip(&event_ids)
+				.map(|(result, event_id)| match result {
+					| Ok(ref short) => self.parse_batch_shorteventid(event_id, short),
+					| Err(_) => Ok(None),
+				})
+				.collect::<Result<_>>()?;
+
+			let (shorts, created) = allocate_missing(&event_ids, stored, |n| {
+				self.services.globals.reserve_counts(n)
+			})?;
+
+			self.create_shorteventids(&created);
+			Ok(stream::iter(shorts).map(Ok::<_, conduwuit::Error>))
+		})
+		.try_flatten()
+}
+
+/// Parses a stored shorteventid found while resolving a batch. A corrupt value
+/// is a database error unless `recreate_corrupt_short_ids` is enabled, in which
+/// case it is logged and treated as missing so a new shorteventid replaces it.
+#[implement(Service)]
+fn parse_batch_shorteventid(
+	&self,
+	event_id: &EventId,
+	short: &[u8],
+) -> Result<Option<ShortEventId>> {
+	match utils::u64_from_bytes(short) {
+		| Ok(short) => Ok(Some(short.into())),
+		| Err(e) if self.services.server.config.recreate_corrupt_short_ids => {
+			warn!(?event_id, "Recreating corrupt shorteventid: {e}");
+			Ok(None)
+		},
+		| Err(e) => Err!(Database(error!(?event_id, "Corrupt shorteventid: {e}"))),
+	}
+}
+
+/// Resolves the shorteventid of each of `event_ids`, allocating any missing,
+/// and splits them by whether the event was already known. Both halves pair
+/// each short with its index into `event_ids`; an event repeated in the batch
+/// falls in the same half each time. Known events are read with a single batch
+/// and all new shorteventids are reserved from the counter at once.
+#[implement(Service)]
+pub async fn multi_classify_shorteventid(
+	&self,
+	event_ids: &[&EventId],
+) -> Result<(Vec<(usize, ShortEventId)>, Vec<(usize, ShortEventId)>)> {
+	let stored: Vec<Option<ShortEventId>> = event_ids
+		.iter()
+		.copied()
+		.stream()
+		.get(&self.db.eventid_shorteventid)
+		.zip(event_ids.iter().stream())
 		.map(|(result, event_id)| match result {
-			| Ok(ref short) => utils::u64_from_u8(short),
-			| Err(_) => self.create_shorteventid(event_id),
+			| Ok(ref short) => self.parse_batch_shorteventid(event_id, short),
+			| Err(e) if e.is_not_found() => Ok(None),
+			| Err(e) => Err(e),
+		})
+		.try_collect()
+		.await?;
+
+	let Classified { known, new, created } =
+		classify_batch(event_ids, stored, |n| self.services.globals.reserve_counts(n))?;
+
+	self.create_shorteventids(&created);
+
+	Ok((known, new))
+}
+
+/// Resolves the shorteventid and shortstatekey of each entry of a state
+/// snapshot, allocating any missing. Both tables are read concurrently with a
+/// batch each and every missing short ID is reserved from the counter at
+/// once, so a snapshot costs a constant number of round-trips rather than
+/// one per entry. Returns the pairs in the order of `entries`.
+#[implement(Service)]
+pub async fn prepare_state_snapshot(
+	&self,
+	entries: &[(OwnedEventId, StateEventType, String)],
+) -> Result<Vec<(ShortEventId, ShortStateKey)>> {
+	let statekeys: Vec<Vec<u8>> = entries
+		.iter()
+		.map(|(_, event_type, state_key)| encode_statekey(event_type, state_key))
+		.collect();
+
+	let shorteventids = entries
+		.iter()
+		.map(|(event_id, ..)| event_id.as_bytes())
+		.stream()
+		.get(&self.db.eventid_shorteventid)
+		.zip(entries.iter().stream())
+		.map(|(result, (event_id, ..))| match result {
+			| Ok(ref short) => self.parse_batch_shorteventid(event_id, short),
+			| Err(e) if e.is_not_found() => Ok(None),
+			| Err(e) => Err(e),
+		})
+		.try_collect::<Vec<Option<ShortEventId>>>();
+
+	let shortstatekeys = statekeys
+		.iter()
+		.stream()
+		.get(&self.db.statekey_shortstatekey)
+		.map(|result| match result {
+			| Ok(ref handle) => handle.deserialized().map(Some),
+			| Err(e) if e.is_not_found() => Ok(None),
+			| Err(e) => Err(e),
+		})
+		.try_collect::<Vec<Option<ShortStateKey>>>();
+
+	let (shorteventids, shortstatekeys) = futures::join!(shorteventids, shortstatekeys);
+	let (shorteventids, shortstatekeys) = (shorteventids?, shortstatekeys?);
+	let event_ids: Vec<&EventId> = entries.iter().map(|(event_id, ..)| &**event_id).collect();
+
+	let statekeys: Vec<&[u8]> = statekeys.iter().map(Vec::as_slice).collect();
+	let Snapshot {
+		shorts,
+		created_events,
+		created_statekeys,
+	} = allocate_snapshot(&event_ids, &statekeys, shorteventids, shortstatekeys, |n| {
+		self.services.globals.reserve_counts(n)
+	})?;
+
+	self.create_shorteventids(&created_events);
+	if !created_statekeys.is_empty() {
+		self.db.statekey_shortstatekey.insert_batch(
+			created_statekeys
+				.iter()
+				.map(|(statekey, short)| (*statekey, short.to_be_bytes())),
+		);
+
+		self.db.shortstatekey_statekey.insert_batch(
+			created_statekeys
+				.iter()
+				.map(|(statekey, short)| (short.to_be_bytes(), *statekey)),
+		);
+	}
+
+	Ok(shorts)
+}
+
+/// Short event IDs of a batch split by whether they were already stored, each
+/// paired with its index into the batch, and those allocated to be stored.
+struct Classified<'a> {
+	known: Vec<(usize, ShortEventId)>,
+	new: Vec<(usize, ShortEventId)>,
+	created: Vec<(&'a EventId, ShortEventId)>,
+}
+
+/// Short IDs of a state snapshot in the order of its entries, and those
+/// allocated to be stored.
+struct Snapshot<'a> {
+	shorts: Vec<(ShortEventId, ShortStateKey)>,
+	created_events: Vec<(&'a EventId, ShortEventId)>,
+	created_statekeys: Vec<(&'a [u8], ShortStateKey)>,
+}
+
+/// Splits `event_ids` by whether their shorteventid was `stored`, allocating
+/// the missing ones from the single range obtained from `reserve`.
+fn classify_batch<'a>(
+	event_ids: &[&'a EventId],
+	stored: Vec<Option<ShortEventId>>,
+	reserve: impl FnOnce(u64) -> Result<Range<u64>>,
+) -> Result<Classified<'a>> {
+	let missing = number_missing(event_ids.iter().copied(), &stored)?;
+	let counts = reserve(u64::try_from(missing.len())?)?;
+	let allocated = |i: u64| ShortEventId(counts.start.saturating_add(i));
+
+	let created = missing
+		.iter()
+		.map(|(event_id, i)| (*event_id, allocated(*i)))
+		.collect();
+
+	let (mut known, mut new) = (Vec::new(), Vec::new());
+	for (i, (event_id, stored)) in event_ids.iter().zip(stored).enumerate() {
+		match stored {
+			| Some(short) => known.push((i, short)),
+			| None => new.push((i, allocated(missing[event_id]))),
+		}
+	}
+
+	Ok(Classified { known, new, created })
+}
+
+/// Resolves the short IDs of each pair of `event_ids` and `statekeys` to the
+/// ones `stored`, allocating the missing ones from the single range obtained
+/// from `reserve`: shorteventids first, then shortstatekeys.
+fn allocate_snapshot<'a>(
+	event_ids: &[&'a EventId],
+	statekeys: &[&'a [u8]],
+	shorteventids: Vec<Option<ShortEventId>>,
+	shortstatekeys: Vec<Option<ShortStateKey>>,
+	reserve: impl FnOnce(u64) -> Result<Range<u64>>,
+) -> Result<Snapshot<'a>> {
+	let missing_events = number_missing(event_ids.iter().copied(), &shorteventids)?;
+	let missing_statekeys = number_missing(statekeys.iter().copied(), &shortstatekeys)?;
+
+	let events = u64::try_from(missing_events.len())?;
+	let counts = reserve(events.saturating_add(u64::try_from(missing_statekeys.len())?))?;
+	let shorteventid = |i: u64| ShortEventId(counts.start.saturating_add(i));
+	let shortstatekey =
+		|i: u64| ShortStateKey(counts.start.saturating_add(events).saturating_add(i));
+
+	let created_events = missing_events
+		.iter()
+		.map(|(event_id, i)| (*event_id, shorteventid(*i)))
+		.collect();
+
+	let created_statekeys = missing_statekeys
+		.iter()
+		.map(|(statekey, i)| (*statekey, shortstatekey(*i)))
+		.collect();
+
+	let shorts = event_ids
+		.iter()
+		.zip(statekeys)
+		.zip(shorteventids.into_iter().zip(shortstatekeys))
+		.map(|((event_id, statekey), (stored_event, stored_statekey))| {
+			(
+				stored_event.unwrap_or_else(|| shorteventid(missing_events[event_id])),
+				stored_statekey.unwrap_or_else(|| shortstatekey(missing_statekeys[statekey])),
+			)
+		})
+		.collect();
+
+	Ok(Snapshot {
+		shorts,
+		created_events,
+		created_statekeys,
+	})
+}
+
+/// Resolves each of `keys` to its `stored` short ID, allocating the missing
+/// ones from the single range obtained from `reserve`. Returns the short IDs in
+/// the order of `keys`, and those allocated to be stored.
+fn allocate_missing<K, S>(
+	keys: &[K],
+	stored: Vec<Option<S>>,
+	reserve: impl FnOnce(u64) -> Result<Range<u64>>,
+) -> Result<(Vec<S>, Vec<(K, S)>)>
+where
+	K: Copy + Eq + Hash,
+	S: From<ShortId>,
+{
+	let missing = number_missing(keys.iter().copied(), &stored)?;
+	let counts = reserve(u64::try_from(missing.len())?)?;
+	let allocated = |i: u64| S::from(counts.start.saturating_add(i));
+
+	let created = missing
+		.iter()
+		.map(|(key, i)| (*key, allocated(*i)))
+		.collect();
+
+	let shorts = keys
+		.iter()
+		.zip(stored)
+		.map(|(key, stored)| stored.unwrap_or_else(|| allocated(missing[key])))
+		.collect();
+
+	Ok((shorts, created))
+}
+
+/// Numbers each distinct key without a `stored` short ID in order of first
+/// occurrence, so that keys repeated in a batch share the short ID allocated
+/// for their first occurrence.
+fn number_missing<K, T>(
+	keys: impl Iterator<Item = K>,
+	stored: &[Option<T>],
+) -> Result<HashMap<K, u64>>
+where
+	K: Eq + Hash,
+{
+	let mut missing = HashMap::new();
+	for (key, stored) in keys.zip(stored) {
+		if stored.is_none() {
+			let next = u64::try_from(missing.len())?;
+			missing.entry(key).or_insert(next);
+		}
+	}
+
+	Ok(missing)
+}
+
+/// Stores many synthetic event ID to shorteventid mappings directly, so that
+/// lookups can be measured against a realistically sized table. The
+/// shorteventids are not allocated from the counter; callers must choose ones
+/// which will not collide with subsequent allocations.
+#[cfg(any(test, feature = "bench"))]
+#[implement(Service)]
+pub fn bulk_seed_shorteventids(&self, pairs: &[(&EventId, ShortEventId)]) {
+	self.create_shorteventids(pairs);
+}
+
+/// Stores the mappings of many newly allocated shorteventids with a single
+/// write batch per table.
+#[implement(Service)]
+fn create_shorteventids(&self, created: &[(&EventId, ShortEventId)]) {
+	if created.is_empty() {
+		return;
+	}
+
+	self.db.eventid_shorteventid.insert_batch(
+		created
+			.iter()
+			.map(|(event_id, short)| (event_id.as_bytes(), short.to_be_bytes())),
+	);
+
+	self.db.shorteventid_eventid.insert_batch(
+		created
+			.iter()
+			.map(|(event_id, short)| (short.to_be_bytes(), event_id.as_bytes())),
+	);
+}
+
+/// The highest short ID allocated in any of the short ID tables.
+#[implement(Service)]
+pub async fn max_allocated_short(&self) -> Result<u64> {
+	let maxes = [
+		max_key(&self.db.shorteventid_eventid).await?,
+		max_key(&self.db.shortstatekey_statekey).await?,
+		max_val(&self.db.roomid_shortroomid).await?,
+		max_val(&self.db.statehash_shortstatehash).await?,
+	];
+
+	Ok(maxes.into_iter().max().unwrap_or(0))
+}
+
+async fn max_key(map: &Arc<Map>) -> Result<u64> {
+	map.rev_raw_keys()
+		.boxed()
+		.next()
+		.await
+		.transpose()?
+		.map_or(Ok(0), utils::u64_from_bytes)
+}
+
+async fn max_val(map: &Arc<Map>) -> Result<u64> {
+	map.raw_stream()
+		.try_fold(0, |max, (_, val)| {
+			future::ready(utils::u64_from_bytes(val).map(|val| max.max(val)))
 		})
+		.await
 }
 
 #[implement(Service)]
-fn create_shorteventid(&self, event_id: &EventId) -> ShortEventId {
+fn create_shorteventid(&self, event_id: &EventId) -> Result<ShortEventId> {
 	const BUFSIZE: usize = size_of::<ShortEventId>();
 
-	let short = self.services.globals.next_count().unwrap();
+	let short = ShortEventId(self.services.globals.next_count()?);
 	debug_assert!(size_of_val(&short) == BUFSIZE, "buffer requirement changed");
 
 	self.db
@@ -93,16 +658,72 @@ fn create_shorteventid(&self, event_id: &EventId) -> ShortEventId {
 		.shorteventid_eventid
 		.aput_raw::<BUFSIZE, _, _>(short, event_id);
 
-	short
+	Ok(short)
 }
 
+/// Returns the shorteventid of an event, failing with not found if it has
+/// none. Nothing is allocated.
 #[implement(Service)]
 pub async fn get_shorteventid(&self, event_id: &EventId) -> Result<ShortEventId> {
-	self.db
+	let result = self
+		.db
 		.eventid_shorteventid
 		.get(event_id)
 		.await
-		.deserialized()
+		.deserialized();
+
+	let Some(fallback) = self.fallback.as_ref() else {
+		return result;
+	};
+
+	match result {
+		| Ok(short) => {
+			fallback
+				.eventid_shorteventid
+				.lock()?
+				.insert(event_id.to_owned(), short);
+
+			Ok(short)
+		},
+		| Err(e) if !e.is_not_found() => {
+			let cached = fallback
+				.eventid_shorteventid
+				.lock()?
+				.get_mut(event_id)
+				.copied();
+
+			match cached {
+				| None => Err(e),
+				| Some(short) => {
+					debug_warn!(
+						?event_id,
+						?short,
+						"Serving cached shorteventid after error: {e}"
+					);
+					Ok(short)
+				},
+			}
+		},
+		| Err(e) => Err(e),
+	}
+}
+
+/// Returns whether each event already has a shorteventid, in the order given.
+/// Unlike `multi_get_or_create_shorteventid` nothing is allocated.
+#[implement(Service)]
+pub async fn multi_has_shorteventid(&self, event_ids: &[&EventId]) -> Result<Vec<bool>> {
+	event_ids
+		.iter()
+		.copied()
+		.stream()
+		.get(&self.db.eventid_shorteventid)
+		.map(|result| match result {
+			| Ok(_) => Ok(true),
+			| Err(e) if e.is_not_found() => Ok(false),
+			| Err(e) => Err(e),
+		})
+		.try_collect()
+		.await
 }
 
 #[implement(Service)]
@@ -110,26 +731,26 @@ pub async fn get_or_create_shortstatekey(
 	&self,
 	event_type: &StateEventType,
 	state_key: &str,
-) -> ShortStateKey {
+) -> Result<ShortStateKey> {
 	const BUFSIZE: usize = size_of::<ShortStateKey>();
 
 	if let Ok(shortstatekey) = self.get_shortstatekey(event_type, state_key).await {
-		return shortstatekey;
+		return Ok(shortstatekey);
 	}
 
-	let key = (event_type, state_key);
-	let shortstatekey = self.services.globals.next_count().unwrap();
+	let key = encode_statekey(event_type, state_key);
+	let shortstatekey = ShortStateKey(self.services.globals.next_count()?);
 	debug_assert!(size_of_val(&shortstatekey) == BUFSIZE, "buffer requirement changed");
 
 	self.db
 		.statekey_shortstatekey
-		.put_aput::<BUFSIZE, _, _>(key, shortstatekey);
+		.raw_aput::<BUFSIZE, _, _>(&key, shortstatekey);
 
 	self.db
 		.shortstatekey_statekey
-		.aput_put::<BUFSIZE, _, _>(shortstatekey, key);
+		.aput_raw::<BUFSIZE, _, _>(shortstatekey, &key);
 
-	shortstatekey
+	Ok(shortstatekey)
 }
 
 #[implement(Service)]
@@ -138,14 +759,56 @@ pub async fn get_shortstatekey(
 	event_type: &StateEventType,
 	state_key: &str,
 ) -> Result<ShortStateKey> {
-	let key = (event_type, state_key);
+	let key = encode_statekey(event_type, state_key);
 	self.db
 		.statekey_shortstatekey
-		.qry(&key)
+		.get(&key)
 		.await
 		.deserialized()
 }
 
+/// Streams every state key allocated under `event_type` with its
+/// shortstatekey, in the order of the `statekey_shortstatekey` table.
+#[implement(Service)]
+pub fn iter_shortstatekeys_by_type<'a>(
+	&'a self,
+	event_type: &StateEventType,
+) -> impl Stream<Item = (ShortStateKey, String)> + Send + 'a {
+	let prefix = encode_statekey(event_type, "");
+	self.db
+		.statekey_shortstatekey
+		.raw_stream_from(&prefix)
+		.ignore_err()
+		.ready_take_while(move |(key, _)| key.starts_with(&prefix))
+		.ready_filter_map(|(key, val)| {
+			let (_, state_key) = decode_statekey(key).ok()?;
+			Some((utils::u64_from_u8(val).into(), state_key))
+		})
+}
+
+/// Every room assigned a shortroomid, ordered by room ID, starting after
+/// `since` when given.
+#[implement(Service)]
+pub fn shortroomids_after<'a>(
+	&'a self,
+	since: Option<&'a RoomId>,
+) -> impl Stream<Item = (OwnedRoomId, ShortRoomId)> + Send + 'a {
+	let since = since.map(RoomId::as_bytes).unwrap_or_default();
+	self.db
+		.roomid_shortroomid
+		.raw_stream_from(since)
+		.ignore_err()
+		.ready_skip_while(move |(key, _)| *key == since)
+		.ready_filter_map(|(key, val)| {
+			let room_id = std::str::from_utf8(key).ok()?.try_into().ok()?;
+			Some((room_id, utils::u64_from_u8(val).into()))
+		})
+}
+
+/// Number of rooms assigned a shortroomid.
+#[implement(Service)]
+pub async fn count_shortroomids(&self) -> usize { self.db.roomid_shortroomid.count().await }
+
 #[implement(Service)]
 pub async fn get_eventid_from_short<Id>(&self, shorteventid: ShortEventId) -> Result<Id>
 where
@@ -154,11 +817,50 @@ pub async fn get_eventid_from_short<Id>(&self, shorteventid: ShortEventId) -> Re
 {
 	const BUFSIZE: usize = size_of::<ShortEventId>();
 
-	self.db
+	let result = self
+		.db
 		.shorteventid_eventid
 		.aqry::<BUFSIZE, _>(&shorteventid)
 		.await
-		.deserialized()
+		.deserialized::<Id>();
+
+	let result = match (self.fallback.as_ref(), result) {
+		| (Some(fallback), Ok(id)) => {
+			let owned = id.to_owned();
+			let event_id: &EventId = owned.borrow();
+			fallback
+				.shorteventid_eventid
+				.lock()?
+				.insert(shorteventid, event_id.to_owned());
+
+			Ok(id)
+		},
+		| (Some(fallback), Err(e)) if !e.is_not_found() => {
+			let cached = fallback
+				.shorteventid_eventid
+				.lock()?
+				.get_mut(&shorteventid)
+				.cloned();
+
+			match cached {
+				| None => Err(e),
+				| Some(event_id) => {
+					debug_warn!(
+						?shorteventid,
+						?event_id,
+						"Serving cached EventId after error: {e}"
+					);
+					let de: value::StrDeserializer<'_, value::Error> =
+						event_id.as_str().into_deserializer();
+
+					Id::deserialize(de).map_err(|e| err!("Failed to convert cached EventId: {e}"))
+				},
+			}
+		},
+		| (_, result) => result,
+	};
+
+	result
 		.map_err(|e| err!(Database("Failed to find EventId from short {shorteventid:?}: {e:?}")))
 }
 
@@ -188,7 +890,7 @@ pub async fn get_statekey_from_short(
 		.shortstatekey_statekey
 		.aqry::<BUFSIZE, _>(&shortstatekey)
 		.await
-		.deserialized()
+		.and_then(|handle| decode_statekey(&handle))
 		.map_err(|e| {
 			err!(Database(
 				"Failed to find (StateEventType, state_key) from short {shortstatekey:?}: {e:?}"
@@ -206,12 +908,36 @@ pub fn multi_get_statekey_from_short<'a, S>(
 {
 	shortstatekey
 		.qry(&self.db.shortstatekey_statekey)
-		.map(Deserialized::deserialized)
+		.map(|result| result.and_then(|handle| decode_statekey(&handle)))
+}
+
+/// Resolves many shortstatekeys with a single batch query, preserving their
+/// order. Fails on the first which is missing or undecodable, naming it.
+#[implement(Service)]
+pub async fn get_statekeys_from_shorts(
+	&self,
+	shortstatekeys: &[ShortStateKey],
+) -> Result<Vec<(StateEventType, String)>> {
+	self.multi_get_statekey_from_short(shortstatekeys.iter().copied().stream())
+		.zip(shortstatekeys.iter().stream())
+		.map(|(result, shortstatekey)| {
+			result.map_err(|e| {
+				err!(Database(
+					"Failed to find (StateEventType, state_key) from short {shortstatekey:?}: \
+					 {e:?}"
+				))
+			})
+		})
+		.try_collect()
+		.await
 }
 
 /// Returns (shortstatehash, already_existed)
 #[implement(Service)]
-pub async fn get_or_create_shortstatehash(&self, state_hash: &[u8]) -> (ShortStateHash, bool) {
+pub async fn get_or_create_shortstatehash(
+	&self,
+	state_hash: &[u8],
+) -> Result<(ShortStateHash, bool)> {
 	const BUFSIZE: usize = size_of::<ShortStateHash>();
 
 	if let Ok(shortstatehash) = self
@@ -221,35 +947,68 @@ pub async fn get_or_create_shortstatehash(&self, state_hash: &[u8]) -> (ShortSta
 		.await
 		.deserialized()
 	{
-		return (shortstatehash, true);
+		return Ok((shortstatehash, true));
 	}
 
-	let shortstatehash = self.services.globals.next_count().unwrap();
+	let shortstatehash = ShortStateHash(self.services.globals.next_count()?);
 	debug_assert!(size_of_val(&shortstatehash) == BUFSIZE, "buffer requirement changed");
 
 	self.db
 		.statehash_shortstatehash
 		.raw_aput::<BUFSIZE, _, _>(state_hash, shortstatehash);
 
-	(shortstatehash, false)
+	self.db
+		.shortstatehash_statehash
+		.aput_raw::<BUFSIZE, _, _>(shortstatehash, state_hash);
+
+	Ok((shortstatehash, false))
 }
 
+/// The state hash a shortstatehash was allocated for, if known. Mappings
+/// predating the reverse table are backfilled by a migration.
 #[implement(Service)]
-pub async fn get_shortroomid(&self, room_id: &RoomId) -> Result<ShortRoomId> {
-	self.db.roomid_shortroomid.get(room_id).await.deserialized()
+pub async fn get_statehash_from_short(
+	&self,
+	shortstatehash: ShortStateHash,
+) -> Result<Option<Vec<u8>>> {
+	match self
+		.db
+		.shortstatehash_statehash
+		.get(&shortstatehash.to_be_bytes())
+		.await
+	{
+		| Ok(state_hash) => Ok(Some(state_hash.to_vec())),
+		| Err(e) if e.is_not_found() => Ok(None),
+		| Err(e) => Err(e),
+	}
 }
 
 #[implement(Service)]
-pub async fn get_or_create_shortroomid(&self, room_id: &RoomId) -> ShortRoomId {
+pub async fn get_shortroomid(&self, room_id: &RoomId) -> Result<ShortRoomId> {
+	if let Some(shortroomid) = self.cached_shortroomid(room_id) {
+		return Ok(shortroomid);
+	}
+
 	self.db
 		.roomid_shortroomid
 		.get(room_id)
 		.await
 		.deserialized()
-		.unwrap_or_else(|_| {
+		.inspect(|&shortroomid| self.cache_shortroomid(room_id, shortroomid))
+}
+
+#[implement(Service)]
+pub async fn get_or_create_shortroomid(&self, room_id: &RoomId) -> Result<ShortRoomId> {
+	if let Some(shortroomid) = self.cached_shortroomid(room_id) {
+		return Ok(shortroomid);
+	}
+
+	let shortroomid = match self.db.roomid_shortroomid.get(room_id).await.deserialized() {
+		| Ok(shortroomid) => shortroomid,
+		| Err(_) => {
 			const BUFSIZE: usize = size_of::<ShortRoomId>();
 
-			let short = self.services.globals.next_count().unwrap();
+			let short = ShortRoomId(self.services.globals.next_count()?);
 			debug_assert!(size_of_val(&short) == BUFSIZE, "buffer requirement changed");
 
 			self.db
@@ -257,5 +1016,162 @@ pub async fn get_or_create_shortroomid(&self, room_id: &RoomId) -> ShortRoomId {
 				.raw_aput::<BUFSIZE, _, _>(room_id, short);
 
 			short
+		},
+	};
+
+	self.cache_shortroomid(room_id, shortroomid);
+	Ok(shortroomid)
+}
+
+/// Batched `get_or_create_shortroomid`: rooms not already cached are read with
+/// a single batch and any missing are allocated with a single write batch, a
+/// room repeated in `room_ids` sharing one. Results are in the order of
+/// `room_ids`.
+#[implement(Service)]
+pub async fn multi_get_or_create_shortroomid(
+	&self,
+	room_ids: &[&RoomId],
+) -> Result<Vec<ShortRoomId>> {
+	let cached: Vec<_> = room_ids
+		.iter()
+		.map(|room_id| self.cached_shortroomid(room_id))
+		.collect();
+
+	let uncached: Vec<&RoomId> = room_ids
+		.iter()
+		.zip(&cached)
+		.filter(|(_, cached)| cached.is_none())
+		.map(|(room_id, _)| *room_id)
+		.collect();
+
+	let stored: Vec<Option<ShortRoomId>> = uncached
+		.iter()
+		.copied()
+		.stream()
+		.get(&self.db.roomid_shortroomid)
+		.map(|result| match result {
+			| Ok(ref handle) => handle.deserialized().map(Some),
+			| Err(e) if e.is_not_found() => Ok(None),
+			| Err(e) => Err(e),
 		})
+		.try_collect()
+		.await?;
+
+	let (allocated, created) =
+		allocate_missing(&uncached, stored, |n| self.services.globals.reserve_counts(n))?;
+
+	let mut allocated = uncached.iter().zip(allocated);
+	let shorts = cached
+		.into_iter()
+		.map(|cached| {
+			cached.unwrap_or_else(|| {
+				let (room_id, shortroomid) =
+					allocated.next().expect("result for each uncached room");
+
+				self.cache_shortroomid(room_id, shortroomid);
+				shortroomid
+			})
+		})
+		.collect();
+
+	if !created.is_empty() {
+		self.db.roomid_shortroomid.insert_batch(
+			created
+				.iter()
+				.map(|(room_id, short)| (room_id.as_bytes(), short.to_be_bytes())),
+		);
+	}
+
+	Ok(shorts)
+}
+
+/// Room of a shortroomid previously resolved by this service, if any.
+#[implement(Service)]
+#[must_use]
+pub fn get_cached_roomid(&self, shortroomid: ShortRoomId) -> Option<OwnedRoomId> {
+	self.rooms
+		.read()
+		.expect("locked for reading")
+		.roomid
+		.get(&shortroomid)
+		.cloned()
+}
+
+#[implement(Service)]
+fn cached_shortroomid(&self, room_id: &RoomId) -> Option<ShortRoomId> {
+	self.rooms
+		.read()
+		.expect("locked for reading")
+		.shortroomid
+		.get(room_id)
+		.copied()
+}
+
+#[implement(Service)]
+fn cache_shortroomid(&self, room_id: &RoomId, shortroomid: ShortRoomId) {
+	let mut rooms = self.rooms.write().expect("locked for writing");
+	rooms.shortroomid.insert(room_id.to_owned(), shortroomid);
+	rooms.roomid.insert(shortroomid, room_id.to_owned());
+}
+
+/// Encodes an (event_type, state_key) pair as stored in the statekey tables:
+/// the LEB128 length of the event type, the event type, then the state key.
+/// Unlike a separator this is unambiguous for any content of either part.
+/// State keys are JSON strings and so always UTF-8; the encoding removes the
+/// ambiguity of the separator and does not admit binary state keys.
+pub(crate) fn encode_statekey(event_type: &StateEventType, state_key: &str) -> Vec<u8> {
+	let event_type: &str = event_type.as_ref();
+	let mut out = Vec::with_capacity(
+		event_type
+			.len()
+			.saturating_add(state_key.len())
+			.saturating_add(2),
+	);
+
+	let mut len = event_type.len();
+	while len >= 0x80 {
+		out.push(u8::try_from(len & 0x7F).expect("masked to seven bits") | 0x80);
+		len >>= 7;
+	}
+
+	out.push(u8::try_from(len).expect("less than 0x80"));
+	out.extend_from_slice(event_type.as_bytes());
+	out.extend_from_slice(state_key.as_bytes());
+	out
+}
+
+/// Decodes an (event_type, state_key) pair produced by `encode_statekey`,
+/// failing if either part is not UTF-8.
+pub(crate) fn decode_statekey(buf: &[u8]) -> Result<(StateEventType, String)> {
+	let mut len: usize = 0;
+	let mut shift: u32 = 0;
+	let mut pos: usize = 0;
+	loop {
+		let Some(&byte) = buf.get(pos) else {
+			return Err!(Database("Truncated length prefix in statekey"));
+		};
+
+		let bits = usize::from(byte & 0x7F)
+			.checked_shl(shift)
+			.ok_or_else(|| err!(Database("Overlong length prefix in statekey")))?;
+
+		len |= bits;
+		pos = pos.saturating_add(1);
+		if byte & 0x80 == 0 {
+			break;
+		}
+
+		shift = shift.saturating_add(7);
+	}
+
+	let (event_type, state_key) = buf
+		.get(pos..)
+		.filter(|rest| rest.len() >= len)
+		.map(|rest| rest.split_at(len))
+		.ok_or_else(|| err!(Database("Event type exceeds length of statekey")))?;
+
+	let event_type = std::str::from_utf8(event_type)?;
+	let state_key = std::str::from_utf8(state_key)?;
+
+	Ok((event_type.into(), state_key.to_owned()))
 }
@@ -0,0 +1,55 @@
+use std::{sync::Arc, time::SystemTime};
+
+use conduwuit::{debug, debug_warn, implement};
+use database::Map;
+
+use super::Service;
+
+/// Number of entries in each of the tables mapping to short IDs.
+#[derive(Clone, Debug)]
+pub struct TableSizes {
+	pub at: SystemTime,
+	pub tables: Vec<(&'static str, u64)>,
+}
+
+/// Counts the entries of each table mapping to short IDs. RocksDB's estimate
+/// is used where available, as counting a large table exactly is expensive;
+/// otherwise the table is counted. The result is retained for
+/// `last_table_sizes`.
+#[implement(Service)]
+pub async fn sample_table_sizes(&self) -> TableSizes {
+	let mut tables = Vec::new();
+	for (name, map) in self.sized_tables() {
+		let entries = match map.property_integer(c"rocksdb.estimate-num-keys") {
+			| Ok(entries) => entries,
+			| Err(e) => {
+				debug_warn!(name, "Counting entries exactly: {e}");
+				map.count().await.try_into().unwrap_or(u64::MAX)
+			},
+		};
+
+		tables.push((name, entries));
+	}
+
+	debug!(?tables, "Sampled short ID table sizes");
+	let sizes = TableSizes { at: SystemTime::now(), tables };
+	*self.last_sizes.write().expect("locked for writing") = Some(sizes.clone());
+	sizes
+}
+
+/// The result of the most recent `sample_table_sizes`, if any since startup.
+#[implement(Service)]
+#[must_use]
+pub fn last_table_sizes(&self) -> Option<TableSizes> {
+	self.last_sizes.read().expect("locked for reading").clone()
+}
+
+#[implement(Service)]
+fn sized_tables(&self) -> [(&'static str, &Arc<Map>); 4] {
+	[
+		("eventid_shorteventid", &self.db.eventid_shorteventid),
+		("statekey_shortstatekey", &self.db.statekey_shortstatekey),
+		("statehash_shortstatehash", &self.db.statehash_shortstatehash),
+		("roomid_shortroomid", &self.db.roomid_shortroomid),
+	]
+}
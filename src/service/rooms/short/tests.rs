@@ -0,0 +1,175 @@
+#![cfg(test)]
+
+use std::collections::HashMap;
+
+use ruma::{event_id, events::StateEventType, room_id, EventId, OwnedEventId};
+
+use super::{
+	allocate_missing, allocate_snapshot, classify_batch, decode_statekey, encode_statekey,
+	ShortEventId, ShortRoomId, ShortStateKey,
+};
+
+#[test]
+fn statekey_roundtrip() {
+	let event_type = StateEventType::RoomMember;
+	let encoded = encode_statekey(&event_type, "@user:example.com");
+	assert_eq!(encoded[0], 13);
+	assert_eq!(decode_statekey(&encoded).unwrap(), (event_type, "@user:example.com".to_owned()));
+}
+
+#[test]
+fn statekey_empty_state_key() {
+	let event_type = StateEventType::RoomCreate;
+	let encoded = encode_statekey(&event_type, "");
+	assert_eq!(decode_statekey(&encoded).unwrap(), (event_type, String::new()));
+}
+
+#[test]
+fn statekey_long_event_type() {
+	let event_type: StateEventType = "x".repeat(300).into();
+	let encoded = encode_statekey(&event_type, "\u{ff}key");
+	assert_eq!(&encoded[..2], &[0xAC, 0x02]);
+	assert_eq!(decode_statekey(&encoded).unwrap(), (event_type, "\u{ff}key".to_owned()));
+}
+
+#[test]
+fn statekey_truncated() {
+	decode_statekey(&[0x80]).unwrap_err();
+	decode_statekey(&[0x05, b'm']).unwrap_err();
+}
+
+#[test]
+fn snapshot_allocation() {
+	let (a, b, c) = (
+		event_id!("$a:example.com"),
+		event_id!("$b:example.com"),
+		event_id!("$c:example.com"),
+	);
+	let known = encode_statekey(&StateEventType::RoomCreate, "");
+	let new = encode_statekey(&StateEventType::RoomMember, "@user:example.com");
+
+	// a is known, b and c are new; each is repeated, as are both state keys.
+	let event_ids = [a, b, a, c, b];
+	let statekeys = [&known[..], &new[..], &new[..], &known[..], &new[..]];
+	let stored_events = [Some(ShortEventId(7)), None, Some(ShortEventId(7)), None, None];
+	let stored_statekeys = [Some(ShortStateKey(3)), None, None, Some(ShortStateKey(3)), None];
+
+	let snapshot = allocate_snapshot(
+		&event_ids,
+		&statekeys,
+		stored_events.to_vec(),
+		stored_statekeys.to_vec(),
+		|n| {
+			assert_eq!(n, 3, "one count per distinct missing event and state key");
+			Ok(100..103)
+		},
+	)
+	.unwrap();
+
+	assert_eq!(snapshot.shorts, [
+		(ShortEventId(7), ShortStateKey(3)),
+		(ShortEventId(100), ShortStateKey(102)),
+		(ShortEventId(7), ShortStateKey(102)),
+		(ShortEventId(101), ShortStateKey(3)),
+		(ShortEventId(100), ShortStateKey(102)),
+	]);
+
+	let mut created_events = snapshot.created_events;
+	created_events.sort_unstable();
+	assert_eq!(created_events, [(b, ShortEventId(100)), (c, ShortEventId(101))]);
+	assert_eq!(snapshot.created_statekeys, [(&new[..], ShortStateKey(102))]);
+}
+
+#[test]
+fn classify_allocation() {
+	let (a, b, c) = (
+		event_id!("$a:example.com"),
+		event_id!("$b:example.com"),
+		event_id!("$c:example.com"),
+	);
+
+	// a is known, b and c are new; a and b are repeated.
+	let event_ids = [a, b, a, c, b];
+	let stored = [Some(ShortEventId(7)), None, Some(ShortEventId(7)), None, None];
+
+	let classified = classify_batch(&event_ids, stored.to_vec(), |n| {
+		assert_eq!(n, 2, "one count per distinct missing event");
+		Ok(100..102)
+	})
+	.unwrap();
+
+	assert_eq!(classified.known, [(0, ShortEventId(7)), (2, ShortEventId(7))]);
+	assert_eq!(classified.new, [
+		(1, ShortEventId(100)),
+		(3, ShortEventId(101)),
+		(4, ShortEventId(100)),
+	]);
+
+	let mut created = classified.created;
+	created.sort_unstable();
+	assert_eq!(created, [(b, ShortEventId(100)), (c, ShortEventId(101))]);
+}
+
+#[test]
+fn repeated_missing_rooms_share_one_short() {
+	let (a, b, c) = (
+		room_id!("!a:example.com"),
+		room_id!("!b:example.com"),
+		room_id!("!c:example.com"),
+	);
+
+	// a is missing and repeated, b is stored and c is missing.
+	let room_ids = [a, b, a, c];
+	let stored = [None, Some(ShortRoomId(5)), None, None];
+
+	let (shorts, mut created) = allocate_missing(&room_ids, stored.to_vec(), |n| {
+		assert_eq!(n, 2, "one count per distinct missing room");
+		Ok(10..12)
+	})
+	.unwrap();
+
+	assert_eq!(shorts, [ShortRoomId(10), ShortRoomId(5), ShortRoomId(10), ShortRoomId(11)]);
+
+	created.sort_unstable();
+	assert_eq!(created, [(a, ShortRoomId(10)), (c, ShortRoomId(11))]);
+}
+
+#[test]
+fn batch_allocation_matches_sequential() {
+	// 700 distinct events, the first 300 repeated; every third already stored.
+	let numbers: Vec<u64> = (0..700).cycle().take(1000).collect();
+	let event_ids: Vec<OwnedEventId> = numbers
+		.iter()
+		.map(|n| EventId::parse(format!("${n}:example.com")).unwrap())
+		.collect();
+	let event_ids: Vec<&EventId> = event_ids.iter().map(AsRef::as_ref).collect();
+	let stored: Vec<_> = numbers
+		.iter()
+		.map(|&n| (n % 3 == 0).then_some(ShortEventId(n)))
+		.collect();
+
+	// The sequential path: one count allocated per event when first missed.
+	let mut count = 10_000_u64;
+	let mut sequential = HashMap::new();
+	let expected: Vec<_> = event_ids
+		.iter()
+		.zip(&stored)
+		.map(|(event_id, stored)| {
+			stored.unwrap_or_else(|| {
+				*sequential.entry(*event_id).or_insert_with(|| {
+					count = count.saturating_add(1);
+					ShortEventId(count)
+				})
+			})
+		})
+		.collect();
+
+	let (shorts, created) = allocate_missing(&event_ids, stored, |n| {
+		assert_eq!(n, u64::try_from(sequential.len()).unwrap(), "one count per distinct event");
+		Ok(10_001..10_001_u64.saturating_add(n))
+	})
+	.unwrap();
+
+	assert_eq!(shorts, expected);
+	assert_eq!(created.into_iter().collect::<HashMap<_, _>>(), sequential);
+}
@@ -1,7 +1,7 @@
 mod tests;
 
 use std::{
-	collections::{HashMap, VecDeque},
+	collections::{HashMap, HashSet, VecDeque},
 	fmt::{Display, Formatter},
 	str::FromStr,
 	sync::Arc,
@@ -65,7 +65,7 @@ fn from_str(value: &str) -> Result<Self> {
 			let rooms = values
 				.next()?
 				.split(',')
-				.filter_map(|room_s| u64::from_str(room_s).ok())
+				.filter_map(|room_s| ShortRoomId::from_str(room_s).ok())
 				.collect();
 
 			Some(Self {
@@ -611,6 +611,30 @@ pub async fn get_client_hierarchy(
 		})
 	}
 
+	/// Returns the space and every room reachable from it through
+	/// m.space.child events, breadth first, using solely local state. The
+	/// children of rooms we have no state for are unknown, so enumeration
+	/// stops at those.
+	pub async fn get_local_descendants(&self, space_id: &RoomId) -> Result<Vec<OwnedRoomId>> {
+		let mut rooms = vec![space_id.to_owned()];
+		let mut seen: HashSet<OwnedRoomId> = rooms.iter().cloned().collect();
+		let mut next = 0_usize;
+		while let Some(room_id) = rooms.get(next).cloned() {
+			next = next.saturating_add(1);
+			let Some(children) = self.get_stripped_space_child_events(&room_id).await? else {
+				continue;
+			};
+
+			for child in children.iter().filter_map(|child| child.deserialize().ok()) {
+				if seen.insert(child.state_key.clone()) {
+					rooms.push(child.state_key);
+				}
+			}
+		}
+
+		Ok(rooms)
+	}
+
 	/// Simply returns the stripped m.space.child events of a room
 	async fn get_stripped_space_child_events(
 		&self,
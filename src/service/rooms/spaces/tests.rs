@@ -9,7 +9,10 @@
 	UInt,
 };
 
-use crate::rooms::spaces::{get_parent_children_via, PaginationToken};
+use crate::rooms::{
+	short::ShortRoomId,
+	spaces::{get_parent_children_via, PaginationToken},
+};
 
 #[test]
 fn get_summary_children() {
@@ -97,7 +100,12 @@ fn invalid_pagination_tokens() {
 fn valid_pagination_tokens() {
 	assert_eq!(
 		PaginationToken {
-			short_room_ids: vec![5383, 42934, 283, 423],
+			short_room_ids: vec![
+				ShortRoomId(5383),
+				ShortRoomId(42934),
+				ShortRoomId(283),
+				ShortRoomId(423)
+			],
 			limit: UInt::from(20_u32),
 			max_depth: UInt::from(1_u32),
 			suggested_only: true
@@ -107,7 +115,7 @@ fn valid_pagination_tokens() {
 
 	assert_eq!(
 		PaginationToken {
-			short_room_ids: vec![740],
+			short_room_ids: vec![ShortRoomId(740)],
 			limit: UInt::from(97_u32),
 			max_depth: UInt::from(10539_u32),
 			suggested_only: false
@@ -120,7 +128,7 @@ fn valid_pagination_tokens() {
 fn pagination_token_to_string() {
 	assert_eq!(
 		PaginationToken {
-			short_room_ids: vec![740],
+			short_room_ids: vec![ShortRoomId(740)],
 			limit: UInt::from(97_u32),
 			max_depth: UInt::from(10539_u32),
 			suggested_only: false
@@ -131,7 +139,7 @@ fn pagination_token_to_string() {
 
 	assert_eq!(
 		PaginationToken {
-			short_room_ids: vec![9, 34],
+			short_room_ids: vec![ShortRoomId(9), ShortRoomId(34)],
 			limit: UInt::from(3_u32),
 			max_depth: UInt::from(1_u32),
 			suggested_only: true
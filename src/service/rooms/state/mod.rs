@@ -4,7 +4,6 @@
 	err,
 	result::FlatOk,
 	utils::{
-		calculate_hash,
 		stream::{BroadbandExt, TryIgnore},
 		IterStream, MutexMap, MutexMapGuard, ReadyExt,
 	},
@@ -28,7 +27,7 @@
 	globals, rooms,
 	rooms::{
 		short::{ShortEventId, ShortStateHash},
-		state_compressor::{parse_compressed_state_event, CompressedState},
+		state_compressor::{compute_state_hash, parse_compressed_state_event, CompressedState},
 	},
 	Dep,
 };
@@ -96,7 +95,7 @@ impl Service {
 	pub async fn force_state(
 		&self,
 		room_id: &RoomId,
-		shortstatehash: u64,
+		shortstatehash: ShortStateHash,
 		statediffnew: Arc<CompressedState>,
 		_statediffremoved: Arc<CompressedState>,
 		state_lock: &RoomMutexGuard,
@@ -179,17 +178,17 @@ pub async fn set_event_state(
 			.services
 			.short
 			.get_or_create_shorteventid(event_id)
-			.await;
+			.await?;
 
 		let previous_shortstatehash = self.get_room_shortstatehash(room_id).await;
 
-		let state_hash = calculate_hash(state_ids_compressed.iter().map(|s| &s[..]));
+		let state_hash = compute_state_hash(&state_ids_compressed);
 
 		let (shortstatehash, already_existed) = self
 			.services
 			.short
 			.get_or_create_shortstatehash(&state_hash)
-			.await;
+			.await?;
 
 		if !already_existed {
 			let states_parents = if let Ok(p) = previous_shortstatehash {
@@ -239,14 +238,14 @@ pub async fn set_event_state(
 	/// This adds all current state events (not including the incoming event)
 	/// to `stateid_pduid` and adds the incoming event to `eventid_statehash`.
 	#[tracing::instrument(skip(self, new_pdu), level = "debug")]
-	pub async fn append_to_state(&self, new_pdu: &PduEvent) -> Result<u64> {
-		const BUFSIZE: usize = size_of::<u64>();
+	pub async fn append_to_state(&self, new_pdu: &PduEvent) -> Result<ShortStateHash> {
+		const BUFSIZE: usize = size_of::<ShortStateHash>();
 
 		let shorteventid = self
 			.services
 			.short
 			.get_or_create_shorteventid(&new_pdu.event_id)
-			.await;
+			.await?;
 
 		let previous_shortstatehash = self.get_room_shortstatehash(&new_pdu.room_id).await;
 
@@ -270,13 +269,13 @@ pub async fn append_to_state(&self, new_pdu: &PduEvent) -> Result<u64> {
 				.services
 				.short
 				.get_or_create_shortstatekey(&new_pdu.kind.to_string().into(), state_key)
-				.await;
+				.await?;
 
 			let new = self
 				.services
 				.state_compressor
 				.compress_state_event(shortstatekey, &new_pdu.event_id)
-				.await;
+				.await?;
 
 			let replaces = states_parents
 				.last()
@@ -292,7 +291,7 @@ pub async fn append_to_state(&self, new_pdu: &PduEvent) -> Result<u64> {
 			}
 
 			// TODO: statehash with deterministic inputs
-			let shortstatehash = self.services.globals.next_count()?;
+			let shortstatehash = ShortStateHash(self.services.globals.next_count()?);
 
 			let mut statediffnew = CompressedState::new();
 			statediffnew.insert(new);
@@ -349,11 +348,11 @@ pub async fn summary_stripped(&self, event: &PduEvent) -> Vec<Raw<AnyStrippedSta
 	pub fn set_room_state(
 		&self,
 		room_id: &RoomId,
-		shortstatehash: u64,
+		shortstatehash: ShortStateHash,
 		_mutex_lock: &RoomMutexGuard, /* Take mutex guard to make sure users get the room
 		                               * state mutex */
 	) {
-		const BUFSIZE: usize = size_of::<u64>();
+		const BUFSIZE: usize = size_of::<ShortStateHash>();
 
 		self.db
 			.roomid_shortstatehash
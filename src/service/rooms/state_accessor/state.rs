@@ -96,8 +96,8 @@ pub async fn state_contains_shortstatekey(
 	shortstatehash: ShortStateHash,
 	shortstatekey: ShortStateKey,
 ) -> bool {
-	let start = compress_state_event(shortstatekey, 0);
-	let end = compress_state_event(shortstatekey, u64::MAX);
+	let start = compress_state_event(shortstatekey, ShortEventId(0));
+	let end = compress_state_event(shortstatekey, ShortEventId(u64::MAX));
 
 	self.load_full_state(shortstatehash)
 		.map_ok(|full_state| full_state.range(start..=end).next().copied())
@@ -162,8 +162,8 @@ pub async fn state_get_shortid(
 		.get_shortstatekey(event_type, state_key)
 		.await?;
 
-	let start = compress_state_event(shortstatekey, 0);
-	let end = compress_state_event(shortstatekey, u64::MAX);
+	let start = compress_state_event(shortstatekey, ShortEventId(0));
+	let end = compress_state_event(shortstatekey, ShortEventId(u64::MAX));
 	self.load_full_state(shortstatehash)
 		.map_ok(|full_state| {
 			full_state
@@ -1,3 +1,5 @@
+mod tests;
+
 use std::{
 	collections::{BTreeSet, HashMap},
 	fmt::{Debug, Write},
@@ -8,7 +10,7 @@
 use arrayvec::ArrayVec;
 use conduwuit::{
 	at, checked, err, expected, utils,
-	utils::{bytes, math::usize_from_f64, stream::IterStream},
+	utils::{bytes, hash::sha256::Digest, math::usize_from_f64, stream::IterStream},
 	Result,
 };
 use database::Map;
@@ -194,7 +196,7 @@ async fn new_shortstatehash_info(
 	pub fn compress_state_events<'a, I>(
 		&'a self,
 		state: I,
-	) -> impl Stream<Item = CompressedStateEvent> + Send + 'a
+	) -> impl Stream<Item = Result<CompressedStateEvent>> + Send + 'a
 	where
 		I: Iterator<Item = (&'a ShortStateKey, &'a EventId)> + Clone + Debug + Send + 'a,
 	{
@@ -210,7 +212,8 @@ pub fn compress_state_events<'a, I>(
 			.map(at!(0))
 			.zip(short_event_ids)
 			.map(|(shortstatekey, shorteventid)| {
-				compress_state_event(*shortstatekey, shorteventid)
+				shorteventid
+					.map(|shorteventid| compress_state_event(*shortstatekey, shorteventid))
 			})
 	}
 
@@ -218,14 +221,14 @@ pub async fn compress_state_event(
 		&self,
 		shortstatekey: ShortStateKey,
 		event_id: &EventId,
-	) -> CompressedStateEvent {
+	) -> Result<CompressedStateEvent> {
 		let shorteventid = self
 			.services
 			.short
 			.get_or_create_shorteventid(event_id)
-			.await;
+			.await?;
 
-		compress_state_event(shortstatekey, shorteventid)
+		Ok(compress_state_event(shortstatekey, shorteventid))
 	}
 
 	/// Creates a new shortstatehash that often is just a diff to an already
@@ -372,14 +375,13 @@ pub async fn save_state(
 			.await
 			.ok();
 
-		let state_hash =
-			utils::calculate_hash(new_state_ids_compressed.iter().map(|bytes| &bytes[..]));
+		let state_hash = compute_state_hash(&new_state_ids_compressed);
 
 		let (new_shortstatehash, already_existed) = self
 			.services
 			.short
 			.get_or_create_shortstatehash(&state_hash)
-			.await;
+			.await?;
 
 		if Some(new_shortstatehash) == previous_shortstatehash {
 			return Ok(HashSetCompressStateEvent {
@@ -445,7 +447,8 @@ async fn get_statediff(&self, shortstatehash: ShortStateHash) -> Result<StateDif
 
 		let parent = utils::u64_from_bytes(&value[0..size_of::<u64>()])
 			.ok()
-			.take_if(|parent| *parent != 0);
+			.take_if(|parent| *parent != 0)
+			.map(ShortStateHash);
 
 		debug_assert!(value.len() % STRIDE == 0, "value not aligned to stride");
 		let _num_values = value.len() / STRIDE;
@@ -483,7 +486,7 @@ fn save_statediff(&self, shortstatehash: ShortStateHash, diff: &StateDiff) {
 				.saturating_add(diff.removed.len()),
 		);
 
-		let parent = diff.parent.unwrap_or(0_u64);
+		let parent = diff.parent.unwrap_or_default();
 		value.extend_from_slice(&parent.to_be_bytes());
 
 		for new in diff.added.iter() {
@@ -503,6 +506,17 @@ fn save_statediff(&self, shortstatehash: ShortStateHash, diff: &StateDiff) {
 	}
 }
 
+/// Canonical hash of a room state, from which its shortstatehash is
+/// allocated by `get_or_create_shortstatehash`. Each compressed event is the
+/// big-endian shortstatekey followed by the big-endian shorteventid; the
+/// events are taken in ascending order, joined by 0xFF and hashed with
+/// SHA-256. All writers of state must hash through here so identical state
+/// always maps to the same shortstatehash.
+#[must_use]
+pub fn compute_state_hash(state: &CompressedState) -> Digest {
+	utils::calculate_hash(state.iter().map(|bytes| &bytes[..]))
+}
+
 #[inline]
 #[must_use]
 pub(crate) fn compress_state_event(
@@ -526,8 +540,8 @@ pub(crate) fn parse_compressed_state_event(
 ) -> (ShortStateKey, ShortEventId) {
 	use utils::u64_from_u8;
 
-	let shortstatekey = u64_from_u8(&compressed_event[0..size_of::<ShortStateKey>()]);
-	let shorteventid = u64_from_u8(&compressed_event[size_of::<ShortStateKey>()..]);
+	let shortstatekey = u64_from_u8(&compressed_event[0..size_of::<ShortStateKey>()]).into();
+	let shorteventid = u64_from_u8(&compressed_event[size_of::<ShortStateKey>()..]).into();
 
 	(shortstatekey, shorteventid)
 }
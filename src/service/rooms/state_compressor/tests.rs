@@ -0,0 +1,52 @@
+#![cfg(test)]
+
+use conduwuit::utils::hash::sha256;
+
+use super::{compress_state_event, compute_state_hash, CompressedState};
+use crate::rooms::short::{ShortEventId, ShortStateKey};
+
+#[test]
+fn state_hash_empty() {
+	let hash = compute_state_hash(&CompressedState::new());
+	assert_eq!(
+		hash[..4],
+		[0xE3, 0xB0, 0xC4, 0x42],
+		"empty state must hash as the SHA-256 of no input"
+	);
+}
+
+#[test]
+fn state_hash_encoding() {
+	let state: CompressedState = [
+		compress_state_event(ShortStateKey(2), ShortEventId(20)),
+		compress_state_event(ShortStateKey(1), ShortEventId(10)),
+	]
+	.into();
+
+	let mut preimage = Vec::new();
+	preimage.extend(1_u64.to_be_bytes());
+	preimage.extend(10_u64.to_be_bytes());
+	preimage.push(0xFF);
+	preimage.extend(2_u64.to_be_bytes());
+	preimage.extend(20_u64.to_be_bytes());
+
+	assert_eq!(
+		compute_state_hash(&state),
+		sha256::hash(&preimage),
+		"events must be hashed in ascending order joined by 0xFF"
+	);
+}
+
+#[test]
+fn state_hash_order_independent() {
+	let events = [(1, 10), (2, 20), (3, 30)]
+		.map(|(key, event)| compress_state_event(ShortStateKey(key), ShortEventId(event)));
+
+	let forward: CompressedState = events.iter().copied().collect();
+	let reverse: CompressedState = events.iter().rev().copied().collect();
+	assert_eq!(
+		compute_state_hash(&forward),
+		compute_state_hash(&reverse),
+		"identical state must hash identically regardless of insertion order"
+	);
+}
@@ -808,7 +808,7 @@ pub async fn create_hash_and_sign_event(
 			.services
 			.short
 			.get_or_create_shorteventid(&pdu.event_id)
-			.await;
+			.await?;
 
 		Ok((pdu, pdu_json))
 	}
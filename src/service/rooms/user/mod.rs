@@ -106,7 +106,7 @@ pub async fn associate_token_shortstatehash(
 		.expect("room exists");
 
 	let _cork = self.db.db.cork();
-	let key: &[u64] = &[shortroomid, token];
+	let key: &[u64] = &[shortroomid.get(), token];
 	self.db
 		.roomsynctoken_shortstatehash
 		.put(key, shortstatehash);
@@ -120,7 +120,7 @@ pub async fn get_token_shortstatehash(
 ) -> Result<ShortStateHash> {
 	let shortroomid = self.services.short.get_shortroomid(room_id).await?;
 
-	let key: &[u64] = &[shortroomid, token];
+	let key: &[u64] = &[shortroomid.get(), token];
 	self.db
 		.roomsynctoken_shortstatehash
 		.qry(key)
@@ -0,0 +1,54 @@
+use std::{collections::HashMap, sync::RwLock, time::SystemTime};
+
+use conduwuit::implement;
+use ruma::{OwnedServerName, ServerName};
+
+/// Outcome of the most recent transactions sent to each remote server.
+#[derive(Default)]
+pub struct Contacts {
+	servers: RwLock<HashMap<OwnedServerName, Contact>>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Contact {
+	/// Time of the last transaction the server accepted.
+	pub last_success: Option<SystemTime>,
+
+	/// Time of the last transaction which failed to reach the server.
+	pub last_failure: Option<SystemTime>,
+}
+
+impl Contact {
+	/// Time of the most recent transaction either way.
+	#[must_use]
+	pub fn last_contact(&self) -> Option<SystemTime> { self.last_success.max(self.last_failure) }
+}
+
+#[implement(Contacts)]
+pub(super) fn record(&self, server: &ServerName, success: bool) {
+	let now = Some(SystemTime::now());
+	let mut servers = self.servers.write().expect("locked for writing");
+	let contact = servers.entry(server.to_owned()).or_default();
+	if success {
+		contact.last_success = now;
+	} else {
+		contact.last_failure = now;
+	}
+}
+
+/// Up to `limit` servers, most recently contacted first.
+#[implement(Contacts)]
+#[must_use]
+pub fn recent(&self, limit: usize) -> Vec<(OwnedServerName, Contact)> {
+	let mut recent: Vec<_> = self
+		.servers
+		.read()
+		.expect("locked for reading")
+		.iter()
+		.map(|(server, contact)| (server.clone(), *contact))
+		.collect();
+
+	recent.sort_unstable_by_key(|(_, contact)| std::cmp::Reverse(contact.last_contact()));
+	recent.truncate(limit);
+	recent
+}
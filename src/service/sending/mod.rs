@@ -1,4 +1,5 @@
 mod appservice;
+pub mod contacts;
 mod data;
 mod dest;
 mod sender;
@@ -24,7 +25,7 @@
 use smallvec::SmallVec;
 use tokio::task::JoinSet;
 
-use self::data::Data;
+use self::{contacts::Contacts, data::Data};
 pub use self::{
 	dest::Destination,
 	sender::{EDU_LIMIT, PDU_LIMIT},
@@ -36,6 +37,7 @@
 
 pub struct Service {
 	pub db: Data,
+	pub contacts: Contacts,
 	server: Arc<Server>,
 	services: Services,
 	channels: Vec<(loole::Sender<Msg>, loole::Receiver<Msg>)>,
@@ -84,6 +86,7 @@ fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
 		let num_senders = num_senders(&args);
 		Ok(Arc::new(Self {
 			db: Data::new(&args),
+			contacts: Contacts::default(),
 			server: args.server.clone(),
 			services: Services {
 				client: args.depend::<client::Service>("client"),
@@ -53,6 +53,7 @@
 use super::{
 	appservice, data::QueueItem, Destination, EduBuf, EduVec, Msg, SendingEvent, Service,
 };
+use crate::resolver::failure::ResolveFailure;
 
 #[derive(Debug)]
 enum TransactionStatus {
@@ -72,6 +73,11 @@ enum TransactionStatus {
 const SELECT_EDU_LIMIT: usize = EDU_LIMIT - 2;
 const DEQUEUE_LIMIT: usize = 48;
 
+/// Failures counted against a destination whose name is known not to resolve,
+/// e.g. NXDOMAIN, backing off to `sender_retry_backoff_limit` at once rather
+/// than retrying soon as after a transient failure.
+const UNRESOLVABLE_TRIES: u32 = 64;
+
 pub const PDU_LIMIT: usize = 50;
 pub const EDU_LIMIT: usize = 100;
 
@@ -135,6 +141,11 @@ async fn handle_response<'a>(
 		futures: &mut SendingFutures<'a>,
 		statuses: &mut CurTransactionStatus,
 	) {
+		let (Ok(dest) | Err((dest, _))) = &response;
+		if let Destination::Federation(server) = dest {
+			self.contacts.record(server, response.is_ok());
+		}
+
 		match response {
 			| Ok(dest) => self.handle_response_ok(&dest, futures, statuses).await,
 			| Err((dest, e)) => Self::handle_response_err(dest, statuses, &e),
@@ -143,11 +154,25 @@ async fn handle_response<'a>(
 
 	fn handle_response_err(dest: Destination, statuses: &mut CurTransactionStatus, e: &Error) {
 		debug!(dest = ?dest, "{e:?}");
+		let unresolvable = ResolveFailure::find(e).filter(|failure| failure.is_permanent());
+		if let Some(failure) = unresolvable {
+			debug!(dest = ?dest, ?failure, "Destination does not resolve, backing off");
+		}
+
+		let tries = |n: u32| {
+			if unresolvable.is_some() {
+				n.max(UNRESOLVABLE_TRIES)
+			} else {
+				n
+			}
+		};
+
 		statuses.entry(dest).and_modify(|e| {
 			*e = match e {
-				| TransactionStatus::Running => TransactionStatus::Failed(1, Instant::now()),
+				| TransactionStatus::Running =>
+					TransactionStatus::Failed(tries(1), Instant::now()),
 				| TransactionStatus::Retrying(ref n) =>
-					TransactionStatus::Failed(n.saturating_add(1), Instant::now()),
+					TransactionStatus::Failed(tries(n.saturating_add(1)), Instant::now()),
 				| TransactionStatus::Failed(..) => {
 					panic!("Request that was not even running failed?!")
 				},
@@ -5,7 +5,7 @@
 	sync::{Arc, RwLock},
 };
 
-use conduwuit::{debug, debug_info, info, trace, Result, Server};
+use conduwuit::{debug, debug_info, error, info, trace, Result, Server};
 use database::Database;
 use tokio::sync::Mutex;
 
@@ -157,6 +157,12 @@ pub async fn stop(&self) {
 			manager.stop().await;
 		}
 
+		if !self.db.is_read_only() {
+			if let Err(e) = self.rooms.short.flush() {
+				error!("Failed to flush short IDs: {e}");
+			}
+		}
+
 		self.admin.set_services(None);
 
 		debug_info!("Services shutdown complete.");
@@ -1,8 +1,12 @@
-use std::{collections::BTreeMap, mem, sync::Arc};
+use std::{
+	collections::BTreeMap,
+	mem,
+	sync::{Arc, RwLock},
+};
 
 use conduwuit::{
 	at, debug_warn, err, trace,
-	utils::{self, stream::TryIgnore, string::Unquoted, ReadyExt},
+	utils::{self, stream::TryIgnore, string::Unquoted, ReadyExt, TryReadyExt},
 	Err, Error, Result, Server,
 };
 use database::{Deserialized, Ignore, Interfix, Json, Map};
@@ -24,6 +28,9 @@
 pub struct Service {
 	services: Services,
 	db: Data,
+
+	/// The result of the last successful `count_local_users`.
+	local_user_count: RwLock<Option<usize>>,
 }
 
 struct Services {
@@ -92,6 +99,7 @@ fn build(args: crate::Args<'_>) -> Result<Arc<Self>> {
 				userid_usersigningkeyid: args.db["userid_usersigningkeyid"].clone(),
 				useridprofilekey_value: args.db["useridprofilekey_value"].clone(),
 			},
+			local_user_count: RwLock::new(None),
 		}))
 	}
 
@@ -203,6 +211,29 @@ pub fn list_local_users(&self) -> impl Stream<Item = &UserId> + Send + '_ {
 			.ready_filter_map(|(u, p): (&UserId, &[u8])| (!p.is_empty()).then_some(u))
 	}
 
+	/// Counts the users `list_local_users` would list, failing on a database
+	/// error rather than skipping the entry. The count is retained for
+	/// `last_local_user_count`.
+	pub async fn count_local_users(&self) -> Result<usize> {
+		let count = self
+			.db
+			.userid_password
+			.stream()
+			.ready_try_fold(0_usize, |count, (_, p): (&UserId, &[u8])| {
+				Ok(count.saturating_add(usize::from(!p.is_empty())))
+			})
+			.await?;
+
+		*self.local_user_count.write()? = Some(count);
+		Ok(count)
+	}
+
+	/// The result of the last successful `count_local_users`, if any.
+	#[must_use]
+	pub fn last_local_user_count(&self) -> Option<usize> {
+		*self.local_user_count.read().expect("locked for reading")
+	}
+
 	/// Returns the password hash for the given user.
 	pub async fn password_hash(&self, user_id: &UserId) -> Result<String> {
 		self.db.userid_password.get(user_id).await.deserialized()